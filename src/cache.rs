@@ -0,0 +1,225 @@
+pub mod cache {
+    use std::{
+        fs,
+        hash::{DefaultHasher, Hash, Hasher},
+        io::{self, ErrorKind},
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use async_trait::async_trait;
+    use yahoo_finance_api::time::OffsetDateTime;
+
+    use crate::log;
+    use crate::quote_provider::quote_provider::{
+        Interval, ProviderError, ProviderKind, QuoteHistory, QuoteProvider,
+    };
+
+    /// The extension of cached responses, so nothing else in the directory is taken for one
+    const EXTENSION: &str = "tickercache";
+
+    /// Where cached responses are kept and for how long
+    #[derive(Debug, Clone)]
+    pub struct ResponseCache {
+        pub dir: PathBuf,
+        /// how long a response is served from the cache before it is fetched again
+        pub ttl: Duration,
+        /// most bytes the cache may take up, the oldest responses are removed to stay under it
+        pub max_bytes: u64,
+    }
+
+    /// What is in the cache directory
+    #[derive(Debug, Default, PartialEq)]
+    pub struct CacheStats {
+        pub entries: usize,
+        pub bytes: u64,
+        /// entries older than the ttl, removed the next time they are asked for
+        pub expired: usize,
+    }
+
+    /// One cached response on disk
+    struct CacheFile {
+        path: PathBuf,
+        bytes: u64,
+        modified: SystemTime,
+    }
+
+    impl ResponseCache {
+        /// the file a response is kept in, named after the source, symbol, interval and the range:
+        /// its dates, so daily runs on the same day share it whatever time of day they start, or
+        /// its unix timestamps for intraday intervals, so a later run picks up the newer bars
+        pub(crate) fn entry(
+            &self,
+            source: &str,
+            symbol: &str,
+            interval: Interval,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+        ) -> PathBuf {
+            let (start, end) = match interval.is_intraday() {
+                true => (
+                    start.unix_timestamp().to_string(),
+                    end.unix_timestamp().to_string(),
+                ),
+                false => (start.date().to_string(), end.date().to_string()),
+            };
+            self.dir.join(format!(
+                "{}_{}_{}_{}_{}.{EXTENSION}",
+                source, symbol, interval, start, end
+            ))
+        }
+
+        /// the cached response in the file, None when there is none or it is older than the ttl
+        pub(crate) fn get(&self, entry: &Path) -> Option<QuoteHistory> {
+            let modified = fs::metadata(entry).and_then(|md| md.modified()).ok()?;
+            if self.is_expired(modified) {
+                let _ = fs::remove_file(entry);
+                return None;
+            }
+            let contents = fs::read_to_string(entry).ok()?;
+            serde_json::from_str(&contents).ok()
+        }
+
+        /// keeps the response in the file, then removes the oldest responses while over the size cap
+        pub(crate) fn put(&self, entry: &Path, history: &QuoteHistory) -> Result<(), String> {
+            let json = serde_json::to_string(history).map_err(|e| e.to_string())?;
+            fs::write(entry, json).map_err(|e| e.to_string())?;
+
+            let mut files = self.files();
+            let mut bytes: u64 = files.iter().map(|file| file.bytes).sum();
+            files.sort_by_key(|file| file.modified);
+            for file in files {
+                if bytes <= self.max_bytes {
+                    break;
+                }
+                evict(&file.path).map_err(|e| e.to_string())?;
+                bytes -= file.bytes;
+            }
+            Ok(())
+        }
+
+        /// counts the responses in the cache directory
+        pub fn stats(&self) -> CacheStats {
+            let files = self.files();
+            CacheStats {
+                entries: files.len(),
+                bytes: files.iter().map(|file| file.bytes).sum(),
+                expired: files
+                    .iter()
+                    .filter(|file| self.is_expired(file.modified))
+                    .count(),
+            }
+        }
+
+        /// removes every response from the cache directory, returns how many were removed
+        pub fn clear(&self) -> usize {
+            let mut removed = 0;
+            for file in self.files() {
+                match fs::remove_file(&file.path) {
+                    Ok(_) => removed += 1,
+                    Err(e) => log("cache", e),
+                }
+            }
+            removed
+        }
+
+        fn is_expired(&self, modified: SystemTime) -> bool {
+            match SystemTime::now().duration_since(modified) {
+                Ok(age) => age > self.ttl,
+                Err(_) => false,
+            }
+        }
+
+        /// the cached responses in the cache directory, leaving any other files alone, json included
+        fn files(&self) -> Vec<CacheFile> {
+            let entries = match fs::read_dir(&self.dir) {
+                Ok(entries) => entries,
+                Err(_) => return Vec::new(),
+            };
+            entries
+                .flatten()
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == EXTENSION))
+                .filter_map(|entry| {
+                    let md = entry.metadata().ok()?;
+                    Some(CacheFile {
+                        path: entry.path(),
+                        bytes: md.len(),
+                        modified: md.modified().ok()?,
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// removes a cached response, one another worker removed first counts as removed
+    pub(crate) fn evict(entry: &Path) -> io::Result<()> {
+        match fs::remove_file(entry) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// where the responses come from as named in the cache: the provider, and for the file
+    /// provider a hash of its data directory so two directories do not share responses
+    pub(crate) fn cache_source(kind: ProviderKind, data_dir: Option<&Path>) -> String {
+        match (kind, data_dir) {
+            (ProviderKind::File, Some(data_dir)) => {
+                let data_dir = fs::canonicalize(data_dir).unwrap_or_else(|_| data_dir.into());
+                let mut hasher = DefaultHasher::new();
+                data_dir.hash(&mut hasher);
+                format!("{}-{:016x}", kind.as_str(), hasher.finish())
+            }
+            _ => String::from(kind.as_str()),
+        }
+    }
+
+    /// Provider that serves repeated requests from the on-disk cache and only asks the inner
+    /// provider on a miss, failures are never cached
+    pub struct CachingProvider {
+        inner: Arc<dyn QuoteProvider>,
+        source: String,
+        cache: ResponseCache,
+    }
+
+    impl CachingProvider {
+        pub fn new(
+            inner: Arc<dyn QuoteProvider>,
+            kind: ProviderKind,
+            data_dir: Option<&Path>,
+            cache: ResponseCache,
+        ) -> Self {
+            CachingProvider {
+                inner,
+                source: cache_source(kind, data_dir),
+                cache,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for CachingProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let entry = self.cache.entry(&self.source, symbol, interval, start, end);
+            if let Some(history) = self.cache.get(&entry) {
+                log(symbol, "served from cache");
+                return Ok(history);
+            }
+
+            let history = self
+                .inner
+                .get_quote_history(symbol, start, end, interval)
+                .await?;
+            if let Err(e) = self.cache.put(&entry, &history) {
+                log(symbol, e);
+            }
+            Ok(history)
+        }
+    }
+}
@@ -0,0 +1,70 @@
+pub mod columnar {
+    use std::{fs::File, path::Path, sync::Arc};
+
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+
+    use crate::symbol_processor::symbol_processor::OutputRow;
+
+    /// the columns of the run-wide file, every symbol's rows share them
+    pub(crate) fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("date", DataType::Utf8, false),
+            Field::new("open", DataType::Float64, false),
+            Field::new("high", DataType::Float64, false),
+            Field::new("low", DataType::Float64, false),
+            Field::new("close", DataType::Float64, false),
+            Field::new("adjclose", DataType::Float64, false),
+            Field::new("volume", DataType::UInt64, false),
+            Field::new("gain", DataType::Float64, false),
+        ])
+    }
+
+    /// puts the rows of every symbol into one record batch, a row per quote
+    pub(crate) fn record_batch(rows: &[(String, OutputRow)]) -> Result<RecordBatch, String> {
+        let float_column = |value: fn(&OutputRow) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(
+                rows.iter().map(|(_, row)| value(row)),
+            ))
+        };
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(
+                rows.iter().map(|(symbol, _)| symbol),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                rows.iter().map(|(_, row)| &row.date),
+            )),
+            float_column(|row| row.quote.open),
+            float_column(|row| row.quote.high),
+            float_column(|row| row.quote.low),
+            float_column(|row| row.quote.close),
+            float_column(|row| row.quote.adjclose),
+            Arc::new(UInt64Array::from_iter_values(
+                rows.iter().map(|(_, row)| row.quote.volume),
+            )),
+            float_column(|row| row.gain),
+        ];
+        RecordBatch::try_new(Arc::new(schema()), columns).map_err(|e| e.to_string())
+    }
+
+    /// writes the batch to a parquet file
+    pub(crate) fn write_parquet(file_name: &Path, batch: &RecordBatch) -> Result<(), String> {
+        let file = File::create(file_name).map_err(|e| e.to_string())?;
+        let mut writer =
+            ArrowWriter::try_new(file, batch.schema(), None).map_err(|e| e.to_string())?;
+        writer.write(batch).map_err(|e| e.to_string())?;
+        writer.close().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// writes the batch to an arrow ipc file
+    pub(crate) fn write_arrow_ipc(file_name: &Path, batch: &RecordBatch) -> Result<(), String> {
+        let file = File::create(file_name).map_err(|e| e.to_string())?;
+        let mut writer = FileWriter::try_new(file, &batch.schema()).map_err(|e| e.to_string())?;
+        writer.write(batch).map_err(|e| e.to_string())?;
+        writer.finish().map_err(|e| e.to_string())
+    }
+}
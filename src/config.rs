@@ -0,0 +1,45 @@
+pub mod config {
+    use std::{fs, path::Path};
+
+    use serde::Deserialize;
+
+    /// Settings read from the --config file, command line options win over these
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    pub struct Config {
+        pub rate_limit: RateLimitConfig,
+        pub postgres: PostgresConfig,
+    }
+
+    /// How many provider requests may be made, shared by every worker of a run
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+    #[serde(default)]
+    pub struct RateLimitConfig {
+        pub per_second: Option<f64>,
+        pub per_minute: Option<f64>,
+    }
+
+    /// Where the postgres format writes the bars of a run
+    #[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+    #[serde(default)]
+    pub struct PostgresConfig {
+        /// connection string, like `host=localhost user=ticker dbname=market`
+        pub url: Option<String>,
+        /// schema holding the table, public when not given
+        pub schema: Option<String>,
+        /// table the bars go in, bars when not given
+        pub table: Option<String>,
+    }
+
+    /// reads the json config file, panics when it cannot be read or understood
+    pub fn read_config(file_name: &Path) -> Config {
+        let contents = match fs::read_to_string(file_name) {
+            Ok(s) => s,
+            Err(_) => panic!("config file cannot be opened"),
+        };
+        match serde_json::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => panic!("config file is not valid: {e}"),
+        }
+    }
+}
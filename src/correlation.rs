@@ -0,0 +1,127 @@
+pub mod correlation {
+    use std::collections::BTreeMap;
+
+    use crate::symbol_processor::symbol_processor::Cell;
+
+    /// The gains of one symbol by date
+    pub(crate) type GainSeries = BTreeMap<String, f64>;
+
+    /// What is worked out between each pair of symbols
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) enum Statistic {
+        Pearson,
+        Spearman,
+        Covariance,
+    }
+
+    impl Statistic {
+        /// every statistic, in the order they are written
+        pub(crate) const ALL: [Statistic; 3] = [
+            Statistic::Pearson,
+            Statistic::Spearman,
+            Statistic::Covariance,
+        ];
+
+        /// the csv file the statistic's matrix is written to
+        pub(crate) fn file_name(&self) -> &'static str {
+            match self {
+                Statistic::Pearson => "pearson.csv",
+                Statistic::Spearman => "spearman.csv",
+                Statistic::Covariance => "covariance.csv",
+            }
+        }
+
+        /// the statistic over the pairs, None when it cannot be worked out
+        pub(crate) fn between(&self, pairs: &[(f64, f64)]) -> Option<f64> {
+            match self {
+                Statistic::Pearson => pearson(pairs),
+                Statistic::Spearman => spearman(pairs),
+                Statistic::Covariance => covariance(pairs),
+            }
+        }
+    }
+
+    /// the gains of both symbols on the dates they both have, pairwise so a day one symbol is
+    /// missing only drops that day from its own pairs
+    pub(crate) fn aligned(a: &GainSeries, b: &GainSeries) -> Vec<(f64, f64)> {
+        a.iter()
+            .filter_map(|(date, gain)| b.get(date).map(|other| (*gain, *other)))
+            .collect()
+    }
+
+    /// sample covariance, None with fewer than 2 pairs
+    pub(crate) fn covariance(pairs: &[(f64, f64)]) -> Option<f64> {
+        if pairs.len() < 2 {
+            return None;
+        }
+
+        let count = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|pair| pair.0).sum::<f64>() / count;
+        let mean_b = pairs.iter().map(|pair| pair.1).sum::<f64>() / count;
+        let products: f64 = pairs.iter().map(|(a, b)| (a - mean_a) * (b - mean_b)).sum();
+        Some(products / (count - 1.0))
+    }
+
+    /// pearson correlation, None with fewer than 2 pairs or when either side never changes
+    pub(crate) fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+        let shared = covariance(pairs)?;
+        let a: Vec<(f64, f64)> = pairs.iter().map(|pair| (pair.0, pair.0)).collect();
+        let b: Vec<(f64, f64)> = pairs.iter().map(|pair| (pair.1, pair.1)).collect();
+        let deviations = (covariance(&a)? * covariance(&b)?).sqrt();
+        if deviations == 0.0 {
+            return None;
+        }
+
+        Some(shared / deviations)
+    }
+
+    /// spearman correlation, the pearson correlation of the ranks
+    pub(crate) fn spearman(pairs: &[(f64, f64)]) -> Option<f64> {
+        let a = ranks(&pairs.iter().map(|pair| pair.0).collect::<Vec<f64>>());
+        let b = ranks(&pairs.iter().map(|pair| pair.1).collect::<Vec<f64>>());
+        let ranked: Vec<(f64, f64)> = a.into_iter().zip(b).collect();
+        pearson(&ranked)
+    }
+
+    /// the rank of each value from 1, tied values share the mean of their ranks
+    pub(crate) fn ranks(values: &[f64]) -> Vec<f64> {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+        let mut ranks = vec![0.0; values.len()];
+        let mut first = 0;
+        while first < order.len() {
+            let mut last = first;
+            while last + 1 < order.len() && values[order[last + 1]] == values[order[first]] {
+                last += 1;
+            }
+            let rank = (first + last) as f64 / 2.0 + 1.0;
+            for index in &order[first..=last] {
+                ranks[*index] = rank;
+            }
+            first = last + 1;
+        }
+        ranks
+    }
+
+    /// the statistic between every pair of symbols, a row and a column per symbol in order with
+    /// the header as the first row, empty where it cannot be worked out
+    pub(crate) fn build_statistic_matrix(
+        series: &BTreeMap<String, GainSeries>,
+        statistic: Statistic,
+    ) -> Vec<Vec<Cell>> {
+        let mut header = vec![Cell::Text(String::from("symbol"))];
+        header.extend(series.keys().map(|symbol| Cell::Text(symbol.clone())));
+        let mut matrix = vec![header];
+        for (symbol, gains) in series {
+            let mut cells = vec![Cell::Text(symbol.clone())];
+            for other in series.values() {
+                cells.push(match statistic.between(&aligned(gains, other)) {
+                    Some(value) => Cell::Number(value),
+                    None => Cell::Text(String::new()),
+                });
+            }
+            matrix.push(cells);
+        }
+        matrix
+    }
+}
@@ -0,0 +1,120 @@
+pub mod database {
+    use std::path::Path;
+
+    use rusqlite::{params, Connection};
+    use yahoo_finance_api::time::OffsetDateTime;
+
+    use crate::symbol_processor::symbol_processor::{OutputRow, RunSettings};
+
+    /// the tables of the database, created the first time a run writes to it
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at INTEGER NOT NULL,
+            start TEXT NOT NULL,
+            end TEXT NOT NULL,
+            interval TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            symbols INTEGER NOT NULL,
+            bars INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS symbols (
+            symbol TEXT PRIMARY KEY,
+            first_run INTEGER NOT NULL REFERENCES runs (id),
+            last_run INTEGER NOT NULL REFERENCES runs (id)
+        );
+        CREATE TABLE IF NOT EXISTS bars (
+            symbol TEXT NOT NULL REFERENCES symbols (symbol),
+            interval TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            open REAL NOT NULL,
+            high REAL NOT NULL,
+            low REAL NOT NULL,
+            close REAL NOT NULL,
+            adjclose REAL NOT NULL,
+            volume INTEGER NOT NULL,
+            gain REAL NOT NULL,
+            metric TEXT NOT NULL,
+            run_id INTEGER NOT NULL REFERENCES runs (id),
+            PRIMARY KEY (symbol, interval, timestamp)
+        );";
+
+    /// writes the rows of every symbol of the run into the sqlite database, returns the id of the run
+    ///
+    /// a bar already in the database for the same symbol, interval and timestamp is replaced, so
+    /// repeated runs build up one history without duplicates
+    pub(crate) fn write_sqlite(
+        file_name: &Path,
+        rows: &[(String, OutputRow)],
+        settings: &RunSettings,
+    ) -> Result<i64, rusqlite::Error> {
+        let mut connection = Connection::open(file_name)?;
+        connection.execute_batch(SCHEMA)?;
+        let transaction = connection.transaction()?;
+
+        let mut symbols: Vec<&str> = rows.iter().map(|(symbol, _)| symbol.as_str()).collect();
+        symbols.dedup();
+        transaction.execute(
+            "INSERT INTO runs (started_at, start, end, interval, metric, symbols, bars)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                OffsetDateTime::now_utc().unix_timestamp(),
+                settings.range.start.date().to_string(),
+                settings.range.end.date().to_string(),
+                settings.interval.as_str(),
+                settings.metric.as_str(),
+                symbols.len() as i64,
+                rows.len() as i64,
+            ],
+        )?;
+        let run_id = transaction.last_insert_rowid();
+
+        {
+            let mut upsert_symbol = transaction.prepare(
+                "INSERT INTO symbols (symbol, first_run, last_run) VALUES (?1, ?2, ?2)
+                 ON CONFLICT (symbol) DO UPDATE SET last_run = excluded.last_run",
+            )?;
+            for symbol in &symbols {
+                upsert_symbol.execute(params![symbol, run_id])?;
+            }
+
+            let mut upsert_bar = transaction.prepare(
+                "INSERT INTO bars (symbol, interval, timestamp, date, open, high, low, close,
+                                   adjclose, volume, gain, metric, run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT (symbol, interval, timestamp) DO UPDATE SET
+                     date = excluded.date,
+                     open = excluded.open,
+                     high = excluded.high,
+                     low = excluded.low,
+                     close = excluded.close,
+                     adjclose = excluded.adjclose,
+                     volume = excluded.volume,
+                     gain = excluded.gain,
+                     metric = excluded.metric,
+                     run_id = excluded.run_id",
+            )?;
+            for (symbol, row) in rows {
+                upsert_bar.execute(params![
+                    symbol,
+                    settings.interval.as_str(),
+                    row.quote.timestamp as i64,
+                    row.date,
+                    row.quote.open,
+                    row.quote.high,
+                    row.quote.low,
+                    row.quote.close,
+                    row.quote.adjclose,
+                    row.quote.volume as i64,
+                    row.gain,
+                    settings.metric.as_str(),
+                    run_id,
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(run_id)
+    }
+}
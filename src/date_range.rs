@@ -0,0 +1,91 @@
+pub mod date_range {
+    use time::{Date, Duration, Month, OffsetDateTime, Time};
+
+    /// how far back the range goes when neither --start nor --window are given
+    const DEFAULT_WINDOW_DAYS: i64 = 30;
+
+    /// the longest window, 10000 years of 365 days, so going back it from any date stays within
+    /// the years a date can have
+    const MAX_WINDOW_DAYS: i64 = 10_000 * 365;
+
+    /// The stretch of time quotes are fetched for, both ends inclusive
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct DateRange {
+        pub start: OffsetDateTime,
+        pub end: OffsetDateTime,
+    }
+
+    impl DateRange {
+        /// works out the range from the command line options
+        ///
+        /// the end is the given end date, or else yesterday (moved back `days_ago` days); the start
+        /// is the given start date, or else `window` (30 days when not given) before the end
+        pub fn resolve(
+            start: Option<Date>,
+            end: Option<Date>,
+            window: Option<Duration>,
+            days_ago: Option<u32>,
+            now: OffsetDateTime,
+        ) -> DateRange {
+            let end = match end {
+                Some(date) => {
+                    date.with_time(Time::MIDNIGHT).assume_utc() + Duration::days(1)
+                        - Duration::seconds(1)
+                }
+                None => now - Duration::days(days_ago.unwrap_or(0) as i64) - Duration::days(1),
+            };
+            let start = match start {
+                Some(date) => date.with_time(Time::MIDNIGHT).assume_utc(),
+                None => end - window.unwrap_or(Duration::days(DEFAULT_WINDOW_DAYS)),
+            };
+            DateRange { start, end }
+        }
+    }
+
+    /// parses a YYYY-MM-DD date
+    pub fn parse_date(text: &str) -> Result<Date, String> {
+        let parts: Vec<&str> = text.trim().split('-').collect();
+        if parts.len() != 3 {
+            return Err(format!("{text} is not a YYYY-MM-DD date"));
+        }
+        let year: i32 = parts[0]
+            .parse()
+            .map_err(|_| format!("{text} has a bad year"))?;
+        let month: u8 = parts[1]
+            .parse()
+            .map_err(|_| format!("{text} has a bad month"))?;
+        let day: u8 = parts[2]
+            .parse()
+            .map_err(|_| format!("{text} has a bad day"))?;
+        let month = Month::try_from(month).map_err(|_| format!("{text} has a bad month"))?;
+        Date::from_calendar_date(year, month, day).map_err(|e| format!("{text}: {e}"))
+    }
+
+    /// parses a window like 90d, 6w, 3mo or 1y, a month is 30 days and a year 365, up to 10000
+    /// years
+    pub fn parse_window(text: &str) -> Result<Duration, String> {
+        let text = text.trim().to_lowercase();
+        let split = text
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or(format!("{text} needs a unit (d, w, mo, y)"))?;
+        let (count, unit) = text.split_at(split);
+        let count: i64 = count
+            .parse()
+            .map_err(|_| format!("{text} needs a number of days, weeks, months or years"))?;
+        let days = match unit {
+            "d" => Some(count),
+            "w" => count.checked_mul(7),
+            "mo" => count.checked_mul(30),
+            "y" => count.checked_mul(365),
+            _ => return Err(format!("{text} has an unknown unit (d, w, mo, y)")),
+        };
+        let days = match days {
+            Some(days) if days <= MAX_WINDOW_DAYS => days,
+            _ => return Err(format!("{text} needs to be at most 10000 years")),
+        };
+        if days <= 0 {
+            return Err(format!("{text} needs to be longer than 0 days"));
+        }
+        Ok(Duration::days(days))
+    }
+}
@@ -0,0 +1,65 @@
+pub mod incremental {
+    use std::{fs, path::Path};
+
+    use serde_json::Value;
+    use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime, Time};
+
+    use crate::date_range::date_range::parse_date;
+    use crate::log;
+    use crate::symbol_processor::symbol_processor::{OutputFormat, OutputRow, RunSettings};
+
+    const OTHER_COLUMNS: &str = "existing output has other columns, writing it again";
+
+    /// the date of the last row already in the symbol's csv or jsonl output, None when there is
+    /// nothing to pick up after (no file, no rows, or rows with other columns than the run's)
+    pub(crate) fn last_stored_date(
+        output_dir: &Path,
+        symbol: &str,
+        settings: &RunSettings,
+    ) -> Option<String> {
+        let format = settings.format;
+        let file_name = match format {
+            OutputFormat::Csv => output_dir.join(symbol),
+            OutputFormat::Jsonl => output_dir.join(format!("{symbol}.jsonl")),
+            _ => return None,
+        };
+        let contents = fs::read_to_string(file_name).ok()?;
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let columns = OutputRow::columns(settings);
+        match format {
+            OutputFormat::Csv => {
+                let header = lines.next()?;
+                if header != columns.join(",") {
+                    log(symbol, OTHER_COLUMNS);
+                    return None;
+                }
+                let last = lines.next_back()?;
+                last.split(',').next().map(String::from)
+            }
+            _ => {
+                let last: Value = serde_json::from_str(lines.next_back()?).ok()?;
+                let keys = last.as_object()?.keys();
+                if !keys.eq(["symbol"]
+                    .into_iter()
+                    .chain(columns.iter().map(String::as_str)))
+                {
+                    log(symbol, OTHER_COLUMNS);
+                    return None;
+                }
+                last["date"].as_str().map(String::from)
+            }
+        }
+    }
+
+    /// where to start fetching to pick up after the stored date
+    ///
+    /// the last stored quote is fetched again so metrics that look back at it have it: intraday
+    /// dates start right at it, plain dates a day early whatever the exchange's utc offset
+    pub(crate) fn resume_start(last_date: &str) -> Option<OffsetDateTime> {
+        if let Ok(timestamp) = OffsetDateTime::parse(last_date, &Rfc3339) {
+            return Some(timestamp);
+        }
+        let date = parse_date(last_date).ok()?;
+        Some(date.with_time(Time::MIDNIGHT).assume_utc() - Duration::days(1))
+    }
+}
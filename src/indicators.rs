@@ -0,0 +1,309 @@
+pub mod indicators {
+    use std::str::FromStr;
+
+    use time::{Duration, OffsetDateTime};
+    use yahoo_finance_api::Quote;
+
+    use crate::quote_provider::quote_provider::Interval;
+
+    /// A technical indicator worked out over the quotes of a symbol and written as extra columns
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Indicator {
+        /// simple moving average of the close over the period
+        Sma(usize),
+        /// exponential moving average of the close, started from the simple average
+        Ema(usize),
+        /// wilder's relative strength index of the close, 0 to 100
+        Rsi(usize),
+        /// the fast less the slow ema of the close, its signal ema and the difference of the two
+        Macd {
+            fast: usize,
+            slow: usize,
+            signal: usize,
+        },
+        /// the simple average of the close and the bands a number of standard deviations around it
+        Bollinger { period: usize, width: f64 },
+        /// wilder's average true range
+        Atr(usize),
+    }
+
+    impl FromStr for Indicator {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let lowered = s.trim().to_lowercase();
+            let mut parts = lowered.split(':');
+            let name = parts.next().unwrap_or_default();
+            let values: Vec<&str> = parts.collect();
+            let period = |index: usize, default: usize| -> Result<usize, String> {
+                match values.get(index) {
+                    None => Ok(default),
+                    Some(value) => match value.parse::<usize>() {
+                        Ok(period) if period > 0 => Ok(period),
+                        _ => Err(format!(
+                            "indicator periods must be whole numbers above 0: {s}"
+                        )),
+                    },
+                }
+            };
+            let most_values = match name {
+                "macd" => 3,
+                "bb" => 2,
+                _ => 1,
+            };
+            if values.len() > most_values {
+                return Err(format!("too many values for the indicator: {s}"));
+            }
+            match name {
+                "sma" => Ok(Indicator::Sma(period(0, 20)?)),
+                "ema" => Ok(Indicator::Ema(period(0, 20)?)),
+                "rsi" => Ok(Indicator::Rsi(period(0, 14)?)),
+                "macd" => {
+                    let (fast, slow, signal) = (period(0, 12)?, period(1, 26)?, period(2, 9)?);
+                    if fast >= slow {
+                        return Err(format!("the macd fast period must be below the slow: {s}"));
+                    }
+                    Ok(Indicator::Macd { fast, slow, signal })
+                }
+                "bb" => {
+                    let width = match values.get(1) {
+                        None => 2.0,
+                        Some(value) => match value.parse::<f64>() {
+                            Ok(width) if width > 0.0 => width,
+                            _ => return Err(format!("bollinger width must be above 0: {s}")),
+                        },
+                    };
+                    Ok(Indicator::Bollinger {
+                        period: period(0, 20)?,
+                        width,
+                    })
+                }
+                "atr" => Ok(Indicator::Atr(period(0, 14)?)),
+                _ => Err(format!(
+                    "unknown indicator: {s} (sma:20, ema:20, rsi:14, macd:12:26:9, bb:20:2, atr:14)"
+                )),
+            }
+        }
+    }
+
+    impl Indicator {
+        /// the names of the columns the indicator is written as
+        pub(crate) fn columns(&self) -> Vec<String> {
+            match self {
+                Indicator::Sma(period) => vec![format!("sma_{period}")],
+                Indicator::Ema(period) => vec![format!("ema_{period}")],
+                Indicator::Rsi(period) => vec![format!("rsi_{period}")],
+                Indicator::Macd { fast, slow, signal } => {
+                    let suffix = format!("{fast}_{slow}_{signal}");
+                    vec![
+                        format!("macd_{suffix}"),
+                        format!("macd_signal_{suffix}"),
+                        format!("macd_hist_{suffix}"),
+                    ]
+                }
+                Indicator::Bollinger { period, .. } => vec![
+                    format!("bb_middle_{period}"),
+                    format!("bb_upper_{period}"),
+                    format!("bb_lower_{period}"),
+                ],
+                Indicator::Atr(period) => vec![format!("atr_{period}")],
+            }
+        }
+
+        /// how many quotes come before the first one the indicator has a value for
+        pub(crate) fn warmup(&self) -> usize {
+            match self {
+                Indicator::Sma(period) | Indicator::Ema(period) => period - 1,
+                Indicator::Bollinger { period, .. } => period - 1,
+                Indicator::Rsi(period) | Indicator::Atr(period) => *period,
+                Indicator::Macd { slow, signal, .. } => slow + signal - 2,
+            }
+        }
+
+        /// the values of each column of the indicator, one per quote, None during the warm-up
+        pub(crate) fn values(&self, quotes: &[Quote]) -> Vec<Vec<Option<f64>>> {
+            let closes: Vec<f64> = quotes.iter().map(|quote| quote.close).collect();
+            match self {
+                Indicator::Sma(period) => vec![sma(&closes, *period)],
+                Indicator::Ema(period) => vec![ema(&closes, *period)],
+                Indicator::Rsi(period) => vec![rsi(&closes, *period)],
+                Indicator::Macd { fast, slow, signal } => {
+                    let fast = ema(&closes, *fast);
+                    let slow = ema(&closes, *slow);
+                    let line: Vec<Option<f64>> = fast
+                        .iter()
+                        .zip(&slow)
+                        .map(|(fast, slow)| Some((*fast)? - (*slow)?))
+                        .collect();
+                    let signal = ema_after_gaps(&line, *signal);
+                    let histogram = line
+                        .iter()
+                        .zip(&signal)
+                        .map(|(line, signal)| Some((*line)? - (*signal)?))
+                        .collect();
+                    vec![line, signal, histogram]
+                }
+                Indicator::Bollinger { period, width } => {
+                    let middle = sma(&closes, *period);
+                    let mut upper = Vec::with_capacity(closes.len());
+                    let mut lower = Vec::with_capacity(closes.len());
+                    for (index, mean) in middle.iter().enumerate() {
+                        match mean {
+                            Some(mean) => {
+                                let window = &closes[index + 1 - period..=index];
+                                let variance = window
+                                    .iter()
+                                    .map(|close| (close - mean).powi(2))
+                                    .sum::<f64>()
+                                    / *period as f64;
+                                upper.push(Some(mean + width * variance.sqrt()));
+                                lower.push(Some(mean - width * variance.sqrt()));
+                            }
+                            None => {
+                                upper.push(None);
+                                lower.push(None);
+                            }
+                        }
+                    }
+                    vec![middle, upper, lower]
+                }
+                Indicator::Atr(period) => vec![atr(quotes, *period)],
+            }
+        }
+    }
+
+    /// the names of the columns of every indicator, in order
+    pub(crate) fn indicator_columns(indicators: &[Indicator]) -> Vec<String> {
+        indicators.iter().flat_map(Indicator::columns).collect()
+    }
+
+    /// the values of every indicator for each quote, in the same order as `indicator_columns`
+    pub(crate) fn indicator_values(
+        indicators: &[Indicator],
+        quotes: &[Quote],
+    ) -> Vec<Vec<Option<f64>>> {
+        let mut rows = vec![Vec::new(); quotes.len()];
+        for indicator in indicators {
+            for column in indicator.values(quotes) {
+                for (row, value) in rows.iter_mut().zip(column) {
+                    row.push(value);
+                }
+            }
+        }
+        rows
+    }
+
+    /// how far before the start to fetch so the indicators have a value from the first quote of
+    /// the range, going by the quotes in a trading year with a week to spare for weekends and holidays
+    pub(crate) fn warmup_start(
+        start: OffsetDateTime,
+        interval: Interval,
+        warmup: usize,
+    ) -> OffsetDateTime {
+        if warmup == 0 {
+            return start;
+        }
+
+        let days = (warmup as f64 * 365.25 / interval.periods_per_year()).ceil() as i64;
+        start - Duration::days(days + 7)
+    }
+
+    fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+        let mut averages = vec![None; values.len()];
+        let mut sum = 0.0;
+        for (index, value) in values.iter().enumerate() {
+            sum += value;
+            if index >= period {
+                sum -= values[index - period];
+            }
+            if index + 1 >= period {
+                averages[index] = Some(sum / period as f64);
+            }
+        }
+        averages
+    }
+
+    fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+        let values: Vec<Option<f64>> = values.iter().copied().map(Some).collect();
+        ema_after_gaps(&values, period)
+    }
+
+    /// the ema of the values from the first one there is, started from the simple average of the
+    /// first period of them
+    fn ema_after_gaps(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+        let mut averages = vec![None; values.len()];
+        let first = match values.iter().position(Option::is_some) {
+            Some(first) => first,
+            None => return averages,
+        };
+        let smoothing = 2.0 / (period as f64 + 1.0);
+        let mut average: Option<f64> = None;
+        let mut sum = 0.0;
+        for (index, value) in values.iter().enumerate().skip(first) {
+            let value = match value {
+                Some(value) => *value,
+                None => continue,
+            };
+            average = match average {
+                Some(average) => Some(average + smoothing * (value - average)),
+                None => {
+                    sum += value;
+                    if index + 1 - first == period {
+                        Some(sum / period as f64)
+                    } else {
+                        None
+                    }
+                }
+            };
+            averages[index] = average;
+        }
+        averages
+    }
+
+    fn rsi(closes: &[f64], period: usize) -> Vec<Option<f64>> {
+        let mut values = vec![None; closes.len()];
+        let mut average_gain = 0.0;
+        let mut average_loss = 0.0;
+        for index in 1..closes.len() {
+            let change = closes[index] - closes[index - 1];
+            let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+            if index <= period {
+                average_gain += gain / period as f64;
+                average_loss += loss / period as f64;
+            } else {
+                average_gain = (average_gain * (period - 1) as f64 + gain) / period as f64;
+                average_loss = (average_loss * (period - 1) as f64 + loss) / period as f64;
+            }
+            if index >= period {
+                values[index] = Some(if average_loss == 0.0 && average_gain == 0.0 {
+                    50.0
+                } else if average_loss == 0.0 {
+                    100.0
+                } else {
+                    100.0 - 100.0 / (1.0 + average_gain / average_loss)
+                });
+            }
+        }
+        values
+    }
+
+    fn atr(quotes: &[Quote], period: usize) -> Vec<Option<f64>> {
+        let mut values = vec![None; quotes.len()];
+        let mut average = 0.0;
+        for index in 1..quotes.len() {
+            let (quote, prior_close) = (&quotes[index], quotes[index - 1].close);
+            let true_range = (quote.high - quote.low)
+                .max((quote.high - prior_close).abs())
+                .max((quote.low - prior_close).abs());
+            if index <= period {
+                average += true_range / period as f64;
+            } else {
+                average = (average * (period - 1) as f64 + true_range) / period as f64;
+            }
+            if index >= period {
+                values[index] = Some(average);
+            }
+        }
+        values
+    }
+}
@@ -1,15 +1,48 @@
-use crate::symbol_processor::symbol_processor::process_symbols;
+use crate::cache::cache::{CachingProvider, ResponseCache};
+use crate::config::config::{read_config, Config, PostgresConfig, RateLimitConfig};
+use crate::date_range::date_range::{parse_date, parse_window, DateRange};
+use crate::indicators::indicators::Indicator;
+use crate::matrix::matrix::FillPolicy;
+use crate::quote_provider::quote_provider::{
+    create_provider, ErrorClass, Interval, ProviderKind, QuoteProvider,
+};
+use crate::rate_limit::rate_limit::RateLimitedProvider;
+use crate::retry::retry::{RetryPolicy, RetryingProvider};
+use crate::screen::screen::{metric_names, Expression};
+use crate::symbol_processor::symbol_processor::{
+    process_symbols, Metric, OutputFormat, OutputMode, RunSettings,
+};
+use crate::vcr::vcr::{RecordingProvider, ReplayProvider};
 use std::{
     fs::{self, OpenOptions},
     io::Write,
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 use structopt::StructOpt;
-use yahoo_finance_api::time::OffsetDateTime;
+use yahoo_finance_api::time::{Date, OffsetDateTime};
 
+mod cache;
+mod columnar;
+mod config;
+mod correlation;
+mod database;
+mod date_range;
+mod incremental;
+mod indicators;
+mod matrix;
+#[cfg(feature = "postgres")]
+mod postgres_sink;
+mod quote_provider;
+mod rate_limit;
+mod retry;
+mod rolling;
+mod screen;
+mod summary;
 mod symbol_processor;
 mod unit_tests;
+mod vcr;
 
 static LOG_FILE_PATH2: Mutex<Option<PathBuf>> = Mutex::new(None::<PathBuf>);
 
@@ -27,30 +60,322 @@ struct Opt {
     output: PathBuf,
     #[structopt(parse(from_os_str), required(true))]
     log_file: PathBuf,
+    /// number of workers fetching symbols at the same time
     #[structopt(short)]
     threads: Option<u8>,
-    #[structopt(short)]
+    #[structopt(short, conflicts_with = "end")]
     days_ago: Option<u32>,
+    /// first day of quotes to get (YYYY-MM-DD)
+    #[structopt(long, parse(try_from_str = parse_date))]
+    start: Option<Date>,
+    /// last day of quotes to get (YYYY-MM-DD), yesterday when not given
+    #[structopt(long, parse(try_from_str = parse_date))]
+    end: Option<Date>,
+    /// how far back from the end to go when --start is not given, like 90d, 6w, 3mo or 1y
+    #[structopt(long, parse(try_from_str = parse_window), conflicts_with = "start")]
+    window: Option<time::Duration>,
+    /// length of time each quote covers (1m, 5m, 15m, 1h, 1d, 1wk, 1mo)
+    #[structopt(long, default_value = "1d")]
+    interval: Interval,
+    /// what goes in each symbol's file: gains, ohlcv (the full quotes) or ohlcv+gains
+    #[structopt(long, default_value = "gains")]
+    output_mode: OutputMode,
+    /// how the gain is worked out: open-close, close-close, adjclose, log, gap, range or total
+    #[structopt(long, default_value = "open-close")]
+    metric: Metric,
+    /// how the output is written: csv, json (SYMBOL.json), jsonl (SYMBOL.jsonl), or parquet
+    /// (ticker.parquet), arrow (ticker.arrow), sqlite (ticker.sqlite), postgres or matrix
+    /// (matrix.csv) for all symbols in one file or table
+    #[structopt(long, default_value = "csv")]
+    format: OutputFormat,
+    /// only fetch the quotes after the last row of each symbol's csv or jsonl file, and add them to it
+    #[structopt(long)]
+    incremental: bool,
+    /// what the matrix format writes for a date a symbol has no quote for: empty, nan or ffill
+    #[structopt(long, default_value = "empty")]
+    fill: FillPolicy,
+    /// write the mean, median, volatility, drawdown, sharpe and sortino of each symbol's gains to
+    /// summary.csv
+    #[structopt(long)]
+    summary: bool,
+    /// write the pearson and spearman correlation and the covariance of every pair of symbols'
+    /// gains to pearson.csv, spearman.csv and covariance.csv
+    #[structopt(long)]
+    correlate: bool,
+    /// technical indicators added as columns of each symbol's csv, json or jsonl file, separated
+    /// by commas: sma:20, ema:20, rsi:14, macd:12:26:9, bb:20:2 or atr:14
+    #[structopt(long, use_delimiter = true)]
+    indicators: Vec<Indicator>,
+    /// lengths of the windows, separated by commas, the rolling mean, standard deviation, z-score,
+    /// min and max of the gain are added as columns of each symbol's csv, json or jsonl file for
+    #[structopt(long, use_delimiter = true)]
+    rolling: Vec<usize>,
+    /// where the quotes come from (yahoo, file)
+    #[structopt(long, default_value = "yahoo")]
+    provider: ProviderKind,
+    /// directory holding SYMBOL.csv or SYMBOL.json quote files for the file provider
+    #[structopt(long, parse(from_os_str))]
+    data_dir: Option<PathBuf>,
+    /// save every provider response as a fixture in this directory
+    #[structopt(long, parse(from_os_str), conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// serve provider responses from the fixtures in this directory instead of the provider
+    #[structopt(long, parse(from_os_str))]
+    replay: Option<PathBuf>,
+    /// most attempts made for each symbol, 1 turns retrying off
+    #[structopt(long, default_value = "3")]
+    max_attempts: u32,
+    /// milliseconds to wait before the first retry, doubled for each retry after that
+    #[structopt(long, default_value = "500")]
+    retry_delay_ms: u64,
+    /// share of each wait (0.0 to 1.0) that is picked at random
+    #[structopt(long, default_value = "0.5")]
+    retry_jitter: f64,
+    /// error classes that are retried (connection, throttled, server, client, data, not_found)
+    #[structopt(
+        long,
+        use_delimiter = true,
+        default_value = "connection,throttled,server"
+    )]
+    retry_on: Vec<ErrorClass>,
+    /// most provider requests per second, shared by all workers
+    #[structopt(long)]
+    rate_per_second: Option<f64>,
+    /// most provider requests per minute, shared by all workers
+    #[structopt(long)]
+    rate_per_minute: Option<f64>,
+    /// postgres connection string for the postgres format, like "host=localhost user=ticker"
+    #[structopt(long)]
+    postgres_url: Option<String>,
+    /// postgres schema the bars table is in, public when not given
+    #[structopt(long)]
+    postgres_schema: Option<String>,
+    /// postgres table the bars go in, bars when not given
+    #[structopt(long)]
+    postgres_table: Option<String>,
+    /// keep successful provider responses in this directory and serve repeated requests from it
+    #[structopt(long, parse(from_os_str))]
+    cache_dir: Option<PathBuf>,
+    /// how long a cached response is used before it is fetched again, like 1d, 2w or 1y
+    #[structopt(long, parse(try_from_str = parse_window), default_value = "1d")]
+    cache_ttl: time::Duration,
+    /// most megabytes the cache may take up, the oldest responses are removed to stay under it
+    #[structopt(long, default_value = "500")]
+    cache_max_mb: u64,
+    /// json file with settings, options given on the command line win over it
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+}
+
+/// Commands that look after the response cache, run as `ticker cache <command>`
+#[derive(StructOpt)]
+#[structopt(
+    name = "ticker cache",
+    about = "looks after the response cache kept with --cache-dir"
+)]
+enum CacheCommand {
+    /// shows how many responses are cached, how much space they take and how many have expired
+    Stats {
+        #[structopt(parse(from_os_str))]
+        cache_dir: PathBuf,
+        /// how long a cached response is used before it is fetched again
+        #[structopt(long, parse(try_from_str = parse_window), default_value = "1d")]
+        cache_ttl: time::Duration,
+    },
+    /// removes every cached response
+    Clear {
+        #[structopt(parse(from_os_str))]
+        cache_dir: PathBuf,
+    },
+}
+
+/// The options of `ticker screen`, the filter and every option of a normal run
+#[derive(StructOpt)]
+#[structopt(name = "ticker screen")]
+struct ScreenOpt {
+    /// filter over each symbol's metrics, like "volatility > 0.3 and last_gain < -0.05"
+    #[structopt(long = "where")]
+    filter: Expression,
+    #[structopt(flatten)]
+    run: Opt,
 }
 
 /// The main method, entry point to the app
 fn main() {
+    let command: Vec<String> = std::env::args().collect();
+    if command.get(1).map(String::as_str) == Some("cache") {
+        let cache_args =
+            std::iter::once("ticker cache").chain(command[2..].iter().map(String::as_str));
+        match CacheCommand::from_iter_safe(cache_args) {
+            Ok(cache_command) => run_cache_command(cache_command),
+            Err(e) => println!("{e}"),
+        }
+        return;
+    }
+
+    if command.get(1).map(String::as_str) == Some("screen") {
+        let screen_args =
+            std::iter::once("ticker screen").chain(command[2..].iter().map(String::as_str));
+        // set after the flattened run options, whose about would otherwise take its place
+        let screen_app = ScreenOpt::clap().about(
+            "fetches the symbols like a normal run and writes the ones whose metrics pass the filter to screen.csv in the output directory",
+        );
+        match screen_app.get_matches_from_safe(screen_args) {
+            Ok(matches) => {
+                let screen_opt = ScreenOpt::from_clap(&matches);
+                run(screen_opt.run, Some(screen_opt.filter))
+            }
+            Err(e) => println!("{e}"),
+        }
+        return;
+    }
+
     let opt = Opt::from_args_safe();
 
     match opt {
-        Ok(args) => {
-            validate_args(&args.file_name, &args.output, &args.log_file, args.threads);
-            let log_file_path = args.log_file.clone();
-            {
-                let mut log_path = LOG_FILE_PATH2.lock().unwrap();
-                *log_path = Some(log_file_path.clone());
+        Ok(args) => run(args, None),
+        Err(e) => println!("{e}"),
+    }
+}
+
+/// fetches the symbols in the input file and writes them out as the options ask, or only the ones
+/// that pass the screen's filter when there is one
+fn run(args: Opt, screen: Option<Expression>) {
+    validate_args(
+        &args.file_name,
+        &args.output,
+        &args.log_file,
+        args.threads,
+        args.start,
+        args.end,
+    );
+    validate_provider_args(args.provider, &args.data_dir, &args.record, &args.replay);
+    validate_retry_args(args.max_attempts, args.retry_jitter);
+    let config = match &args.config {
+        Some(file_name) => read_config(file_name),
+        None => Config::default(),
+    };
+    let rate_limit = RateLimitConfig {
+        per_second: args.rate_per_second.or(config.rate_limit.per_second),
+        per_minute: args.rate_per_minute.or(config.rate_limit.per_minute),
+    };
+    validate_rate_limit(rate_limit);
+    let postgres = PostgresConfig {
+        url: args.postgres_url.or(config.postgres.url),
+        schema: args.postgres_schema.or(config.postgres.schema),
+        table: args.postgres_table.or(config.postgres.table),
+    };
+    validate_postgres(args.format, &postgres);
+    validate_incremental(args.incremental, args.format);
+    validate_indicators(&args.indicators, args.format);
+    validate_rolling(&args.rolling, args.format);
+    validate_cache_dir(&args.cache_dir);
+    let now = OffsetDateTime::now_utc();
+    let range = DateRange::resolve(args.start, args.end, args.window, args.days_ago, now);
+    validate_range(&range);
+    let earliest = match args.replay {
+        Some(_) => None,
+        None => {
+            validate_lookback(args.provider, args.interval, &range, now);
+            args.provider
+                .max_lookback(args.interval)
+                .map(|max_lookback| now - max_lookback)
+        }
+    };
+    let log_file_path = args.log_file.clone();
+    {
+        let mut log_path = LOG_FILE_PATH2.lock().unwrap();
+        *log_path = Some(log_file_path.clone());
+    }
+    let file_contents = read_file(&args.file_name);
+    let symbols = get_ticker_symbols(&file_contents);
+    let mut provider: Arc<dyn QuoteProvider> = match args.replay {
+        Some(dir) => Arc::new(ReplayProvider::new(dir)),
+        None => {
+            let policy = RetryPolicy {
+                max_attempts: args.max_attempts,
+                base_delay: Duration::from_millis(args.retry_delay_ms),
+                jitter: args.retry_jitter,
+                retry_on: args.retry_on,
+            };
+            let mut provider = create_provider(args.provider, args.data_dir.clone());
+            if rate_limit != RateLimitConfig::default() {
+                provider = Arc::new(RateLimitedProvider::new(provider, rate_limit));
+            }
+            provider = Arc::new(RetryingProvider::new(provider, policy));
+            if let Some(dir) = args.cache_dir {
+                let cache = ResponseCache {
+                    dir,
+                    ttl: to_std_duration(args.cache_ttl),
+                    max_bytes: args.cache_max_mb * 1024 * 1024,
+                };
+                provider = Arc::new(CachingProvider::new(
+                    provider,
+                    args.provider,
+                    args.data_dir.as_deref(),
+                    cache,
+                ));
             }
-            let file_contents = read_file(&args.file_name);
-            let symbols = get_ticker_symbols(&file_contents);
-            process_symbols(symbols, &args.output, args.threads, args.days_ago);
+            provider
         }
-        Err(e) => println!("{e}"),
+    };
+    if let Some(dir) = args.record {
+        provider = Arc::new(RecordingProvider::new(provider, dir));
     }
+    let settings = RunSettings {
+        range,
+        interval: args.interval,
+        output_mode: args.output_mode,
+        metric: args.metric,
+        format: args.format,
+        fill: args.fill,
+        incremental: args.incremental,
+        summary: args.summary,
+        correlate: args.correlate,
+        indicators: args.indicators,
+        rolling: args.rolling,
+        screen,
+        earliest,
+        #[cfg(feature = "postgres")]
+        postgres,
+    };
+    validate_screen(&settings);
+    process_symbols(symbols, &args.output, args.threads, settings, provider);
+}
+
+/// runs one of the `ticker cache` commands and prints what it did
+fn run_cache_command(cache_command: CacheCommand) {
+    match cache_command {
+        CacheCommand::Stats {
+            cache_dir,
+            cache_ttl,
+        } => {
+            validate_cache_dir(&Some(cache_dir.clone()));
+            let cache = ResponseCache {
+                dir: cache_dir,
+                ttl: to_std_duration(cache_ttl),
+                max_bytes: u64::MAX,
+            };
+            let stats = cache.stats();
+            println!("entries: {}", stats.entries);
+            println!("size: {:.1} MB", stats.bytes as f64 / (1024.0 * 1024.0));
+            println!("expired: {}", stats.expired);
+        }
+        CacheCommand::Clear { cache_dir } => {
+            validate_cache_dir(&Some(cache_dir.clone()));
+            let cache = ResponseCache {
+                dir: cache_dir,
+                ttl: Duration::ZERO,
+                max_bytes: u64::MAX,
+            };
+            println!("removed {} entries", cache.clear());
+        }
+    }
+}
+
+/// turns a duration from the command line into a std duration, 0 when it is negative
+fn to_std_duration(duration: time::Duration) -> Duration {
+    Duration::try_from(duration).unwrap_or(Duration::ZERO)
 }
 
 /// convenience function to log trouble without interrupting things
@@ -97,11 +422,24 @@ fn validate_args(
     output_dir: &PathBuf,
     log_file: &PathBuf,
     threads: Option<u8>,
+    start: Option<Date>,
+    end: Option<Date>,
 ) {
     if threads != None && threads < Some(2) {
         panic!("threads needs to be more than 1");
     }
 
+    let today = OffsetDateTime::now_utc().date();
+    if start > Some(today) || end > Some(today) {
+        panic!("dates cannot be in the future");
+    }
+
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            panic!("start needs to be on or before end");
+        }
+    }
+
     let file_exists = Path::exists(file_name);
     if !file_exists {
         panic!("file_name does not exist");
@@ -141,6 +479,168 @@ fn validate_args(
     }
 }
 
+/// Method that makes sure the provider has what it needs to get quotes
+fn validate_provider_args(
+    provider: ProviderKind,
+    data_dir: &Option<PathBuf>,
+    record: &Option<PathBuf>,
+    replay: &Option<PathBuf>,
+) {
+    if let Some(dir) = replay {
+        if !dir.is_dir() {
+            panic!("replay directory does not exist");
+        }
+        return;
+    }
+
+    if let Some(dir) = record {
+        if !dir.is_dir() {
+            panic!("record directory does not exist");
+        }
+    }
+
+    if provider == ProviderKind::File {
+        match data_dir {
+            None => panic!("data_dir is needed for the file provider"),
+            Some(dir) => {
+                if !dir.is_dir() {
+                    panic!("data directory does not exist");
+                }
+            }
+        }
+    }
+}
+
+/// Method that makes sure the retry settings make sense
+fn validate_retry_args(max_attempts: u32, retry_jitter: f64) {
+    if max_attempts < 1 {
+        panic!("max_attempts needs to be at least 1");
+    }
+
+    if !(0.0..=1.0).contains(&retry_jitter) {
+        panic!("retry_jitter needs to be between 0.0 and 1.0");
+    }
+}
+
+/// Method that makes sure the rate limits are usable
+fn validate_rate_limit(rate_limit: RateLimitConfig) {
+    for limit in [rate_limit.per_second, rate_limit.per_minute]
+        .into_iter()
+        .flatten()
+    {
+        if limit <= 0.0 {
+            panic!("rate limits need to be more than 0");
+        }
+    }
+}
+
+/// Method that makes sure the postgres format has somewhere to write to
+fn validate_postgres(format: OutputFormat, postgres: &PostgresConfig) {
+    if format != OutputFormat::Postgres {
+        return;
+    }
+
+    if !cfg!(feature = "postgres") {
+        panic!("the postgres format needs ticker built with --features postgres");
+    }
+
+    if postgres.url.is_none() {
+        panic!("postgres_url is needed for the postgres format");
+    }
+}
+
+/// Method that makes sure indicators are only asked for with a format that has room for their columns
+fn validate_indicators(indicators: &[Indicator], format: OutputFormat) {
+    let per_symbol = matches!(
+        format,
+        OutputFormat::Csv | OutputFormat::Json | OutputFormat::Jsonl
+    );
+    if !indicators.is_empty() && !per_symbol {
+        panic!("indicators need the csv, json or jsonl format");
+    }
+}
+
+/// Method that makes sure the resolved range does not end before it starts, which a --start
+/// after the default end (yesterday, or earlier with -d) would otherwise slip past validate_args
+fn validate_range(range: &DateRange) {
+    if range.start > range.end {
+        panic!("start needs to be on or before end");
+    }
+}
+
+/// Method that makes sure the screen's filter only uses metrics the run works out, and that the
+/// run writes nothing the screen would skip
+fn validate_screen(settings: &RunSettings) {
+    let filter = match &settings.screen {
+        Some(filter) => filter,
+        None => return,
+    };
+    let names = metric_names(settings);
+    for metric in filter.metrics() {
+        if !names.iter().any(|name| name == metric) {
+            panic!(
+                "unknown metric in the filter: {metric} (known: {})",
+                names.join(", ")
+            );
+        }
+    }
+    if settings.incremental {
+        panic!("incremental cannot be used with ticker screen");
+    }
+    if settings.format != OutputFormat::Csv {
+        panic!("ticker screen only writes screen.csv, the format cannot be set");
+    }
+}
+
+/// Method that makes sure rolling windows are long enough to have a spread, and only asked for
+/// with a format that has room for their columns
+fn validate_rolling(windows: &[usize], format: OutputFormat) {
+    if windows.iter().any(|window| *window < 2) {
+        panic!("rolling windows must be at least 2 long");
+    }
+    let per_symbol = matches!(
+        format,
+        OutputFormat::Csv | OutputFormat::Json | OutputFormat::Jsonl
+    );
+    if !windows.is_empty() && !per_symbol {
+        panic!("rolling windows need the csv, json or jsonl format");
+    }
+}
+
+/// Method that makes sure incremental runs write a format rows can be added to
+fn validate_incremental(incremental: bool, format: OutputFormat) {
+    if incremental && format != OutputFormat::Csv && format != OutputFormat::Jsonl {
+        panic!("incremental needs the csv or jsonl format");
+    }
+}
+
+/// Method that makes sure the cache directory is there
+fn validate_cache_dir(cache_dir: &Option<PathBuf>) {
+    if let Some(dir) = cache_dir {
+        if !dir.is_dir() {
+            panic!("cache directory does not exist");
+        }
+    }
+}
+
+/// Method that makes sure the provider keeps quotes at the interval as far back as the range starts
+fn validate_lookback(
+    provider: ProviderKind,
+    interval: Interval,
+    range: &DateRange,
+    now: OffsetDateTime,
+) {
+    if let Some(max_lookback) = provider.max_lookback(interval) {
+        if now - range.start > max_lookback {
+            panic!(
+                "the {} interval only goes back {} days",
+                interval,
+                max_lookback.whole_days()
+            );
+        }
+    }
+}
+
 /// method to separate ticker symbols from a text string
 fn get_ticker_symbols<'a>(test_data: &'a str) -> Vec<&'a str> {
     let mut ret = Vec::new();
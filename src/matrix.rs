@@ -0,0 +1,88 @@
+pub mod matrix {
+    use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+    use csv::WriterBuilder;
+
+    use crate::symbol_processor::symbol_processor::{Cell, OutputRow};
+
+    /// What goes in the matrix where a symbol has no quote for a date
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FillPolicy {
+        /// leave the cell empty
+        Empty,
+        /// write NaN
+        NaN,
+        /// repeat the symbol's last value, empty before its first one
+        ForwardFill,
+    }
+
+    impl FromStr for FillPolicy {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "empty" => Ok(FillPolicy::Empty),
+                "nan" => Ok(FillPolicy::NaN),
+                "ffill" => Ok(FillPolicy::ForwardFill),
+                _ => Err(format!("unknown fill policy: {s} (empty, nan, ffill)")),
+            }
+        }
+    }
+
+    /// lines the gains of every symbol up on the dates any of them has, a row per date and a
+    /// column per symbol, both in order, with the header as the first row
+    pub(crate) fn build_matrix(rows: &[(String, OutputRow)], fill: FillPolicy) -> Vec<Vec<Cell>> {
+        let mut by_date: BTreeMap<&str, BTreeMap<&str, f64>> = BTreeMap::new();
+        let mut symbols: Vec<&str> = Vec::new();
+        for (symbol, row) in rows {
+            by_date
+                .entry(row.date.as_str())
+                .or_default()
+                .insert(symbol.as_str(), row.gain);
+            symbols.push(symbol.as_str());
+        }
+        symbols.sort();
+        symbols.dedup();
+
+        let mut header = vec![Cell::Text(String::from("date"))];
+        header.extend(
+            symbols
+                .iter()
+                .map(|symbol| Cell::Text(String::from(*symbol))),
+        );
+        let mut matrix = vec![header];
+        let mut last: Vec<Option<f64>> = vec![None; symbols.len()];
+        for (date, gains) in by_date {
+            let mut cells = vec![Cell::Text(String::from(date))];
+            for (column, symbol) in symbols.iter().enumerate() {
+                let value = match (gains.get(symbol), fill) {
+                    (Some(gain), _) => Some(*gain),
+                    (None, FillPolicy::NaN) => Some(f64::NAN),
+                    (None, FillPolicy::ForwardFill) => last[column],
+                    (None, FillPolicy::Empty) => None,
+                };
+                if let Some(gain) = gains.get(symbol) {
+                    last[column] = Some(*gain);
+                }
+                cells.push(match value {
+                    Some(value) => Cell::Number(value),
+                    None => Cell::Text(String::new()),
+                });
+            }
+            matrix.push(cells);
+        }
+        matrix
+    }
+
+    /// writes the matrix to a csv file
+    pub(crate) fn write_matrix(file_name: &Path, matrix: Vec<Vec<Cell>>) -> Result<(), csv::Error> {
+        let mut writer = WriterBuilder::new()
+            .has_headers(false)
+            .from_path(file_name)?;
+        for row in matrix {
+            writer.serialize(row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
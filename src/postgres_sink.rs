@@ -0,0 +1,144 @@
+pub mod postgres_sink {
+    use std::pin::pin;
+
+    use tokio_postgres::{binary_copy::BinaryCopyInWriter, types::Type, NoTls};
+
+    use crate::config::config::PostgresConfig;
+    use crate::log;
+    use crate::symbol_processor::symbol_processor::{OutputRow, RunSettings};
+
+    /// the columns copied for every bar, in order
+    const COLUMNS: [(&str, Type); 12] = [
+        ("symbol", Type::TEXT),
+        ("interval", Type::TEXT),
+        ("timestamp", Type::INT8),
+        ("date", Type::TEXT),
+        ("open", Type::FLOAT8),
+        ("high", Type::FLOAT8),
+        ("low", Type::FLOAT8),
+        ("close", Type::FLOAT8),
+        ("adjclose", Type::FLOAT8),
+        ("volume", Type::INT8),
+        ("gain", Type::FLOAT8),
+        ("metric", Type::TEXT),
+    ];
+
+    /// puts double quotes around a name so any schema or table name can be used as given
+    pub(crate) fn quote_identifier(name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    /// the quoted schema and table name the bars go in
+    pub(crate) fn table_name(config: &PostgresConfig) -> String {
+        let schema = config.schema.as_deref().unwrap_or("public");
+        let table = config.table.as_deref().unwrap_or("bars");
+        format!("{}.{}", quote_identifier(schema), quote_identifier(table))
+    }
+
+    /// creates the schema and table when they are not there yet
+    pub(crate) fn create_table_sql(config: &PostgresConfig) -> String {
+        let schema = config.schema.as_deref().unwrap_or("public");
+        format!(
+            "CREATE SCHEMA IF NOT EXISTS {};
+             CREATE TABLE IF NOT EXISTS {} (
+                 symbol TEXT NOT NULL,
+                 interval TEXT NOT NULL,
+                 timestamp BIGINT NOT NULL,
+                 date TEXT NOT NULL,
+                 open DOUBLE PRECISION NOT NULL,
+                 high DOUBLE PRECISION NOT NULL,
+                 low DOUBLE PRECISION NOT NULL,
+                 close DOUBLE PRECISION NOT NULL,
+                 adjclose DOUBLE PRECISION NOT NULL,
+                 volume BIGINT NOT NULL,
+                 gain DOUBLE PRECISION NOT NULL,
+                 metric TEXT NOT NULL,
+                 PRIMARY KEY (symbol, interval, timestamp)
+             );",
+            quote_identifier(schema),
+            table_name(config)
+        )
+    }
+
+    /// moves the copied bars from the staging table into the table, replacing bars already there
+    ///
+    /// a bar copied twice (a symbol listed twice in the input) goes in once, as the upsert cannot
+    /// touch the same row twice
+    pub(crate) fn upsert_sql(config: &PostgresConfig) -> String {
+        let columns: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        let updates: Vec<String> = columns[3..]
+            .iter()
+            .map(|name| format!("{name} = excluded.{name}"))
+            .collect();
+        format!(
+            "INSERT INTO {} ({columns})
+             SELECT DISTINCT ON ({key}) {columns} FROM ticker_staging ORDER BY {key}
+             ON CONFLICT ({key}) DO UPDATE SET {}",
+            table_name(config),
+            updates.join(", "),
+            columns = columns.join(", "),
+            key = columns[..3].join(", ")
+        )
+    }
+
+    /// copies the rows of every symbol of the run into postgres, returns how many bars were written
+    ///
+    /// the rows are copied into a staging table first and then upserted on (symbol, interval,
+    /// timestamp), so writing the same bars again leaves one copy of each
+    pub(crate) async fn write_postgres(
+        config: &PostgresConfig,
+        rows: &[(String, OutputRow)],
+        settings: &RunSettings,
+    ) -> Result<u64, tokio_postgres::Error> {
+        let url = config.url.as_deref().unwrap_or_default();
+        let (mut client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log("ticker", e);
+            }
+        });
+
+        client.batch_execute(&create_table_sql(config)).await?;
+        let transaction = client.transaction().await?;
+        transaction
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE ticker_staging (LIKE {} INCLUDING DEFAULTS) ON COMMIT DROP",
+                table_name(config)
+            ))
+            .await?;
+
+        let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        let types: Vec<Type> = COLUMNS.iter().map(|(_, column)| column.clone()).collect();
+        let sink = transaction
+            .copy_in(&format!(
+                "COPY ticker_staging ({}) FROM STDIN BINARY",
+                names.join(", ")
+            ))
+            .await?;
+        let mut writer = pin!(BinaryCopyInWriter::new(sink, &types));
+        for (symbol, row) in rows {
+            writer
+                .as_mut()
+                .write(&[
+                    symbol,
+                    &settings.interval.as_str(),
+                    &(row.quote.timestamp as i64),
+                    &row.date,
+                    &row.quote.open,
+                    &row.quote.high,
+                    &row.quote.low,
+                    &row.quote.close,
+                    &row.quote.adjclose,
+                    &(row.quote.volume as i64),
+                    &row.gain,
+                    &settings.metric.as_str(),
+                ])
+                .await?;
+        }
+        writer.finish().await?;
+
+        let written = transaction.execute(&upsert_sql(config), &[]).await?;
+        transaction.commit().await?;
+        Ok(written)
+    }
+}
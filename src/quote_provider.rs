@@ -0,0 +1,415 @@
+pub mod quote_provider {
+    use std::{
+        fmt, fs,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::Arc,
+    };
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+    use yahoo_finance_api::{
+        time::{Duration, OffsetDateTime},
+        Quote, YahooConnector, YahooError,
+    };
+
+    /// The ways fetching quotes from a provider can go wrong
+    #[derive(Debug, Serialize, Deserialize)]
+    pub enum ProviderError {
+        /// the provider could not be set up or could not be reached
+        Connection(String),
+        /// the provider was reached but refused or failed the request
+        Fetch(String),
+        /// the provider answered but the answer could not be turned into quotes
+        Data(String),
+        /// the provider has nothing for the symbol
+        NotFound(String),
+    }
+
+    impl fmt::Display for ProviderError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ProviderError::Connection(e) => write!(f, "connection failed: {e}"),
+                ProviderError::Fetch(e) => write!(f, "fetch failed: {e}"),
+                ProviderError::Data(e) => write!(f, "bad data: {e}"),
+                ProviderError::NotFound(e) => write!(f, "not found: {e}"),
+            }
+        }
+    }
+
+    /// Broad kinds of provider errors, used to decide which ones are worth trying again
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ErrorClass {
+        /// the provider could not be reached, including timeouts
+        Connection,
+        /// the provider is throttling requests (http 429)
+        Throttled,
+        /// the provider had a problem of its own (http 5xx)
+        Server,
+        /// the provider refused the request (any other http status)
+        Client,
+        /// the answer could not be turned into quotes
+        Data,
+        /// the provider has nothing for the symbol
+        NotFound,
+    }
+
+    impl FromStr for ErrorClass {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "connection" => Ok(ErrorClass::Connection),
+                "throttled" => Ok(ErrorClass::Throttled),
+                "server" => Ok(ErrorClass::Server),
+                "client" => Ok(ErrorClass::Client),
+                "data" => Ok(ErrorClass::Data),
+                "not_found" => Ok(ErrorClass::NotFound),
+                _ => Err(format!("unknown error class: {s}")),
+            }
+        }
+    }
+
+    impl ProviderError {
+        /// sorts the error into its class, fetch errors are sorted by their http status
+        pub fn class(&self) -> ErrorClass {
+            match self {
+                ProviderError::Connection(_) => ErrorClass::Connection,
+                ProviderError::Fetch(status) => {
+                    if status.starts_with("429") {
+                        ErrorClass::Throttled
+                    } else if status.starts_with('5') {
+                        ErrorClass::Server
+                    } else {
+                        ErrorClass::Client
+                    }
+                }
+                ProviderError::Data(_) => ErrorClass::Data,
+                ProviderError::NotFound(_) => ErrorClass::NotFound,
+            }
+        }
+    }
+
+    impl From<YahooError> for ProviderError {
+        fn from(e: YahooError) -> Self {
+            match e {
+                YahooError::ConnectionFailed(inner) => ProviderError::Connection(inner.to_string()),
+                YahooError::BuilderFailed => ProviderError::Connection(e.to_string()),
+                YahooError::FetchFailed(status) => ProviderError::Fetch(status),
+                _ => ProviderError::Data(e.to_string()),
+            }
+        }
+    }
+
+    /// A stock split, `numerator` shares for every `denominator` shares held before it
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SplitEvent {
+        pub timestamp: u64,
+        pub numerator: f64,
+        pub denominator: f64,
+    }
+
+    /// A cash dividend per share, paid to whoever held the share before its ex-dividend date
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct DividendEvent {
+        pub timestamp: u64,
+        pub amount: f64,
+    }
+
+    /// The quotes a provider handed back for a symbol
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct QuoteHistory {
+        pub quotes: Vec<Quote>,
+        /// seconds the exchange's local time is ahead of utc
+        #[serde(default)]
+        pub utc_offset: i32,
+        /// splits that took effect during the range
+        #[serde(default)]
+        pub splits: Vec<SplitEvent>,
+        /// dividends that went ex during the range
+        #[serde(default)]
+        pub dividends: Vec<DividendEvent>,
+    }
+
+    impl QuoteHistory {
+        /// quotes with timestamps in utc and no splits or dividends
+        pub fn new(quotes: Vec<Quote>) -> Self {
+            QuoteHistory {
+                quotes,
+                utc_offset: 0,
+                splits: Vec::new(),
+                dividends: Vec::new(),
+            }
+        }
+    }
+
+    /// A source of historical quotes for a ticker symbol, shared by every fetch of a run
+    #[async_trait]
+    pub trait QuoteProvider: Send + Sync {
+        /// get the quotes for the symbol from start to end (inclusive), one per interval
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError>;
+    }
+
+    /// The length of time each quote (bar) covers
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub enum Interval {
+        #[serde(rename = "1m")]
+        OneMinute,
+        #[serde(rename = "5m")]
+        FiveMinutes,
+        #[serde(rename = "15m")]
+        FifteenMinutes,
+        #[serde(rename = "1h")]
+        OneHour,
+        #[serde(rename = "1d")]
+        OneDay,
+        #[serde(rename = "1wk")]
+        OneWeek,
+        #[serde(rename = "1mo")]
+        OneMonth,
+    }
+
+    impl Interval {
+        /// the name yahoo finance uses for the interval
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Interval::OneMinute => "1m",
+                Interval::FiveMinutes => "5m",
+                Interval::FifteenMinutes => "15m",
+                Interval::OneHour => "1h",
+                Interval::OneDay => "1d",
+                Interval::OneWeek => "1wk",
+                Interval::OneMonth => "1mo",
+            }
+        }
+
+        /// how many quotes there are in a trading year, 252 days of 6.5 hours
+        pub fn periods_per_year(&self) -> f64 {
+            match self {
+                Interval::OneMinute => 98280.0,
+                Interval::FiveMinutes => 19656.0,
+                Interval::FifteenMinutes => 6552.0,
+                Interval::OneHour => 1638.0,
+                Interval::OneDay => 252.0,
+                Interval::OneWeek => 52.0,
+                Interval::OneMonth => 12.0,
+            }
+        }
+
+        /// whether a quote covers less than a day
+        pub fn is_intraday(&self) -> bool {
+            matches!(
+                self,
+                Interval::OneMinute
+                    | Interval::FiveMinutes
+                    | Interval::FifteenMinutes
+                    | Interval::OneHour
+            )
+        }
+    }
+
+    impl fmt::Display for Interval {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.as_str())
+        }
+    }
+
+    impl FromStr for Interval {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "1m" => Ok(Interval::OneMinute),
+                "5m" => Ok(Interval::FiveMinutes),
+                "15m" => Ok(Interval::FifteenMinutes),
+                "1h" => Ok(Interval::OneHour),
+                "1d" => Ok(Interval::OneDay),
+                "1wk" => Ok(Interval::OneWeek),
+                "1mo" => Ok(Interval::OneMonth),
+                _ => Err(format!(
+                    "unknown interval: {s} (1m, 5m, 15m, 1h, 1d, 1wk, 1mo)"
+                )),
+            }
+        }
+    }
+
+    /// The providers that can be picked from the command line
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ProviderKind {
+        Yahoo,
+        File,
+    }
+
+    impl FromStr for ProviderKind {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.to_lowercase().as_str() {
+                "yahoo" => Ok(ProviderKind::Yahoo),
+                "file" => Ok(ProviderKind::File),
+                _ => Err(format!("unknown provider: {s}")),
+            }
+        }
+    }
+
+    impl ProviderKind {
+        /// the name the provider is picked by
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                ProviderKind::Yahoo => "yahoo",
+                ProviderKind::File => "file",
+            }
+        }
+
+        /// how far back from today the provider keeps quotes at the interval, None when there is no limit
+        pub fn max_lookback(&self, interval: Interval) -> Option<Duration> {
+            match self {
+                ProviderKind::Yahoo => match interval {
+                    Interval::OneMinute => Some(Duration::days(7)),
+                    Interval::FiveMinutes | Interval::FifteenMinutes => Some(Duration::days(60)),
+                    Interval::OneHour => Some(Duration::days(730)),
+                    _ => None,
+                },
+                ProviderKind::File => None,
+            }
+        }
+    }
+
+    /// creates the provider for the kind picked on the command line
+    pub fn create_provider(
+        kind: ProviderKind,
+        data_dir: Option<PathBuf>,
+    ) -> Arc<dyn QuoteProvider> {
+        match kind {
+            ProviderKind::Yahoo => match YahooProvider::new() {
+                Ok(provider) => Arc::new(provider),
+                Err(e) => panic!("{e}"),
+            },
+            ProviderKind::File => match data_dir {
+                Some(dir) => Arc::new(FileProvider::new(dir)),
+                None => panic!("data_dir is needed for the file provider"),
+            },
+        }
+    }
+
+    /// The default provider, gets the quotes from yahoo finance over one shared connection
+    pub struct YahooProvider {
+        connector: YahooConnector,
+    }
+
+    impl YahooProvider {
+        pub fn new() -> Result<Self, ProviderError> {
+            Ok(YahooProvider {
+                connector: YahooConnector::new()?,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for YahooProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let response = self
+                .connector
+                .get_quote_history_interval(symbol, start, end, interval.as_str())
+                .await?;
+            Ok(QuoteHistory {
+                quotes: response.quotes()?,
+                utc_offset: response.metadata()?.gmtoffset,
+                splits: response
+                    .splits()?
+                    .into_iter()
+                    .map(|split| SplitEvent {
+                        timestamp: split.date,
+                        numerator: split.numerator,
+                        denominator: split.denominator,
+                    })
+                    .collect(),
+                dividends: response
+                    .dividends()?
+                    .into_iter()
+                    .map(|dividend| DividendEvent {
+                        timestamp: dividend.date,
+                        amount: dividend.amount,
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    /// Provider that replays quotes stored on disk, one SYMBOL.csv or SYMBOL.json file per symbol
+    ///
+    /// the files are served at whatever interval they were stored at, with utc timestamps
+    pub struct FileProvider {
+        data_dir: PathBuf,
+    }
+
+    impl FileProvider {
+        pub fn new(data_dir: PathBuf) -> Self {
+            FileProvider { data_dir }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for FileProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            _interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let csv_file = self.data_dir.join(format!("{symbol}.csv"));
+            let json_file = self.data_dir.join(format!("{symbol}.json"));
+            let quotes = if csv_file.exists() {
+                read_csv_quotes(&csv_file)?
+            } else if json_file.exists() {
+                read_json_quotes(&json_file)?
+            } else {
+                return Err(ProviderError::NotFound(format!(
+                    "no data file for {symbol} in {}",
+                    self.data_dir.display()
+                )));
+            };
+
+            let first = start.unix_timestamp().max(0) as u64;
+            let last = end.unix_timestamp().max(0) as u64;
+            Ok(QuoteHistory::new(
+                quotes
+                    .into_iter()
+                    .filter(|q| q.timestamp >= first && q.timestamp <= last)
+                    .collect(),
+            ))
+        }
+    }
+
+    /// reads quotes from a csv file with a timestamp,open,high,low,close,volume,adjclose header
+    pub(crate) fn read_csv_quotes(file_name: &Path) -> Result<Vec<Quote>, ProviderError> {
+        let mut reader =
+            csv::Reader::from_path(file_name).map_err(|e| ProviderError::Data(e.to_string()))?;
+        let mut quotes = Vec::new();
+        for record in reader.deserialize() {
+            let quote: Quote = record.map_err(|e| ProviderError::Data(e.to_string()))?;
+            quotes.push(quote);
+        }
+        Ok(quotes)
+    }
+
+    /// reads quotes from a json file holding an array of quote objects
+    pub(crate) fn read_json_quotes(file_name: &Path) -> Result<Vec<Quote>, ProviderError> {
+        let contents =
+            fs::read_to_string(file_name).map_err(|e| ProviderError::Data(e.to_string()))?;
+        serde_json::from_str(&contents).map_err(|e| ProviderError::Data(e.to_string()))
+    }
+}
@@ -0,0 +1,133 @@
+pub mod rate_limit {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use async_trait::async_trait;
+    use yahoo_finance_api::time::OffsetDateTime;
+
+    use crate::config::config::RateLimitConfig;
+    use crate::quote_provider::quote_provider::{
+        Interval, ProviderError, QuoteHistory, QuoteProvider,
+    };
+
+    /// A bucket that holds up to `capacity` tokens and gains `per_second` tokens every second
+    #[derive(Debug)]
+    pub struct TokenBucket {
+        capacity: f64,
+        per_second: f64,
+        tokens: f64,
+        last_refill: Instant,
+    }
+
+    impl TokenBucket {
+        /// a full bucket allowing `requests` requests every `period`
+        pub fn new(requests: f64, period: Duration, now: Instant) -> Self {
+            let capacity = requests.max(1.0);
+            TokenBucket {
+                capacity,
+                per_second: requests / period.as_secs_f64(),
+                tokens: capacity,
+                last_refill: now,
+            }
+        }
+
+        fn refill(&mut self, now: Instant) {
+            let elapsed = now
+                .saturating_duration_since(self.last_refill)
+                .as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+            self.last_refill = now;
+        }
+
+        /// how long until a token is available, zero when there is one now
+        pub fn wait_time(&mut self, now: Instant) -> Duration {
+            self.refill(now);
+            if self.tokens >= 1.0 {
+                return Duration::ZERO;
+            }
+            Duration::from_secs_f64((1.0 - self.tokens) / self.per_second)
+        }
+
+        /// takes a token, only call after wait_time said one is there
+        pub fn take(&mut self) {
+            self.tokens -= 1.0;
+        }
+    }
+
+    /// Token buckets for each configured limit, a request needs a token from all of them
+    pub struct RateLimiter {
+        buckets: Mutex<Vec<TokenBucket>>,
+    }
+
+    impl RateLimiter {
+        pub fn new(config: RateLimitConfig) -> Self {
+            let now = Instant::now();
+            let mut buckets = Vec::new();
+            if let Some(per_second) = config.per_second {
+                buckets.push(TokenBucket::new(per_second, Duration::from_secs(1), now));
+            }
+            if let Some(per_minute) = config.per_minute {
+                buckets.push(TokenBucket::new(per_minute, Duration::from_secs(60), now));
+            }
+            RateLimiter {
+                buckets: Mutex::new(buckets),
+            }
+        }
+
+        /// waits until every bucket has a token, then takes one from each
+        pub async fn acquire(&self) {
+            loop {
+                let wait = {
+                    let mut buckets = self.buckets.lock().unwrap();
+                    let now = Instant::now();
+                    let wait = buckets
+                        .iter_mut()
+                        .map(|bucket| bucket.wait_time(now))
+                        .max()
+                        .unwrap_or(Duration::ZERO);
+                    if wait.is_zero() {
+                        buckets.iter_mut().for_each(|bucket| bucket.take());
+                    }
+                    wait
+                };
+                if wait.is_zero() {
+                    return;
+                }
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+
+    /// Provider that holds every request to another provider until the rate limiter lets it through
+    pub struct RateLimitedProvider {
+        inner: Arc<dyn QuoteProvider>,
+        limiter: RateLimiter,
+    }
+
+    impl RateLimitedProvider {
+        pub fn new(inner: Arc<dyn QuoteProvider>, config: RateLimitConfig) -> Self {
+            RateLimitedProvider {
+                inner,
+                limiter: RateLimiter::new(config),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for RateLimitedProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            self.limiter.acquire().await;
+            self.inner
+                .get_quote_history(symbol, start, end, interval)
+                .await
+        }
+    }
+}
@@ -0,0 +1,111 @@
+pub mod retry {
+    use std::{sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+    use yahoo_finance_api::time::OffsetDateTime;
+
+    use crate::log;
+    use crate::quote_provider::quote_provider::{
+        ErrorClass, Interval, ProviderError, QuoteHistory, QuoteProvider,
+    };
+
+    /// the longest a single wait between attempts is allowed to get
+    const MAX_DELAY: Duration = Duration::from_secs(60);
+
+    /// How hard to try before giving up on a symbol
+    #[derive(Debug, Clone)]
+    pub struct RetryPolicy {
+        /// total number of attempts, including the first one
+        pub max_attempts: u32,
+        /// wait before the second attempt, doubled for every attempt after that
+        pub base_delay: Duration,
+        /// share of each wait (0.0 to 1.0) that is picked at random, so workers do not retry in lockstep
+        pub jitter: f64,
+        /// the error classes that are worth another attempt
+        pub retry_on: Vec<ErrorClass>,
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(500),
+                jitter: 0.5,
+                retry_on: vec![
+                    ErrorClass::Connection,
+                    ErrorClass::Throttled,
+                    ErrorClass::Server,
+                ],
+            }
+        }
+    }
+
+    impl RetryPolicy {
+        /// the wait before the given attempt (2 is the first retry) without any jitter
+        pub fn backoff(&self, attempt: u32) -> Duration {
+            let doublings = attempt.saturating_sub(2).min(31);
+            self.base_delay
+                .saturating_mul(1 << doublings)
+                .min(MAX_DELAY)
+        }
+
+        /// the wait before the given attempt with a random part taken off
+        pub fn delay(&self, attempt: u32) -> Duration {
+            let backoff = self.backoff(attempt);
+            let jitter = self.jitter.clamp(0.0, 1.0) * fastrand::f64();
+            backoff.mul_f64(1.0 - jitter)
+        }
+
+        /// whether the error is worth another attempt
+        pub fn is_retryable(&self, error: &ProviderError) -> bool {
+            self.retry_on.contains(&error.class())
+        }
+    }
+
+    /// Provider that tries another provider again, with exponential backoff, when it fails with a retryable error
+    pub struct RetryingProvider {
+        inner: Arc<dyn QuoteProvider>,
+        policy: RetryPolicy,
+    }
+
+    impl RetryingProvider {
+        pub fn new(inner: Arc<dyn QuoteProvider>, policy: RetryPolicy) -> Self {
+            RetryingProvider { inner, policy }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for RetryingProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let mut attempt = 1;
+            loop {
+                match self
+                    .inner
+                    .get_quote_history(symbol, start, end, interval)
+                    .await
+                {
+                    Ok(quotes) => {
+                        if attempt > 1 {
+                            log(symbol, format!("attempt {attempt} succeeded"));
+                        }
+                        return Ok(quotes);
+                    }
+                    Err(e) => {
+                        log(symbol, format!("attempt {attempt} failed: {e}"));
+                        if attempt >= self.policy.max_attempts || !self.policy.is_retryable(&e) {
+                            return Err(e);
+                        }
+                    }
+                }
+                attempt += 1;
+                tokio::time::sleep(self.policy.delay(attempt)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+pub mod rolling {
+    /// the names of the columns written for each window, in order
+    pub(crate) fn rolling_columns(windows: &[usize]) -> Vec<String> {
+        windows
+            .iter()
+            .flat_map(|window| {
+                ["mean", "std", "z", "min", "max"]
+                    .iter()
+                    .map(move |statistic| format!("gain_{statistic}_{window}"))
+            })
+            .collect()
+    }
+
+    /// the rolling statistics of the gains over each window for each gain, the window ending at
+    /// and including the gain, in the same order as `rolling_columns` and None until the window
+    /// is full
+    pub(crate) fn rolling_values(windows: &[usize], gains: &[f64]) -> Vec<Vec<Option<f64>>> {
+        let mut rows = vec![Vec::new(); gains.len()];
+        for window in windows {
+            for (index, row) in rows.iter_mut().enumerate() {
+                if index + 1 < *window {
+                    row.extend([None; 5]);
+                    continue;
+                }
+                let values = &gains[index + 1 - window..=index];
+                let mean = values.iter().sum::<f64>() / *window as f64;
+                let squares: f64 = values.iter().map(|gain| (gain - mean).powi(2)).sum();
+                let std_dev = (squares / (*window - 1) as f64).sqrt();
+                let z = match std_dev == 0.0 {
+                    true => None,
+                    false => Some((gains[index] - mean) / std_dev),
+                };
+                let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                row.extend([Some(mean), Some(std_dev), z, Some(min), Some(max)]);
+            }
+        }
+        rows
+    }
+}
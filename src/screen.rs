@@ -0,0 +1,289 @@
+pub mod screen {
+    use std::{path::Path, str::FromStr};
+
+    use csv::WriterBuilder;
+
+    use crate::indicators::indicators::indicator_columns;
+    use crate::rolling::rolling::rolling_columns;
+    use crate::summary::summary::{summarise, Summary};
+    use crate::symbol_processor::symbol_processor::{Cell, OutputRow, RunSettings};
+
+    /// The metrics of one symbol by name, in the same order as `metric_names`
+    pub(crate) type Metrics = Vec<(String, Option<f64>)>;
+
+    /// How two values are compared
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Comparison {
+        Less,
+        LessOrEqual,
+        Greater,
+        GreaterOrEqual,
+        Equal,
+        NotEqual,
+    }
+
+    impl Comparison {
+        fn holds(&self, left: f64, right: f64) -> bool {
+            match self {
+                Comparison::Less => left < right,
+                Comparison::LessOrEqual => left <= right,
+                Comparison::Greater => left > right,
+                Comparison::GreaterOrEqual => left >= right,
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+            }
+        }
+    }
+
+    /// One side of a comparison
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Operand {
+        Number(f64),
+        Metric(String),
+    }
+
+    /// A filter over the metrics of a symbol, like `volatility > 0.3 and last_gain < -0.05`
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Expression {
+        Compare(Operand, Comparison, Operand),
+        Not(Box<Expression>),
+        And(Box<Expression>, Box<Expression>),
+        Or(Box<Expression>, Box<Expression>),
+    }
+
+    impl FromStr for Expression {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut parser = Parser {
+                tokens: tokenize(s)?,
+                position: 0,
+            };
+            let expression = parser.or()?;
+            match parser.tokens.get(parser.position) {
+                None => Ok(expression),
+                Some(token) => Err(format!("unexpected {token} in the filter: {s}")),
+            }
+        }
+    }
+
+    impl Expression {
+        /// whether the symbol's metrics pass the filter, a comparison with a metric the symbol
+        /// has no value for never holds
+        pub(crate) fn matches(&self, metrics: &Metrics) -> bool {
+            match self {
+                Expression::Compare(left, comparison, right) => {
+                    match (value(left, metrics), value(right, metrics)) {
+                        (Some(left), Some(right)) => comparison.holds(left, right),
+                        _ => false,
+                    }
+                }
+                Expression::Not(inner) => !inner.matches(metrics),
+                Expression::And(left, right) => left.matches(metrics) && right.matches(metrics),
+                Expression::Or(left, right) => left.matches(metrics) || right.matches(metrics),
+            }
+        }
+
+        /// the names of the metrics the filter uses
+        pub(crate) fn metrics(&self) -> Vec<&str> {
+            match self {
+                Expression::Compare(left, _, right) => [left, right]
+                    .into_iter()
+                    .filter_map(|operand| match operand {
+                        Operand::Metric(name) => Some(name.as_str()),
+                        Operand::Number(_) => None,
+                    })
+                    .collect(),
+                Expression::Not(inner) => inner.metrics(),
+                Expression::And(left, right) | Expression::Or(left, right) => {
+                    let mut names = left.metrics();
+                    names.extend(right.metrics());
+                    names
+                }
+            }
+        }
+    }
+
+    fn value(operand: &Operand, metrics: &Metrics) -> Option<f64> {
+        match operand {
+            Operand::Number(number) => Some(*number),
+            Operand::Metric(name) => metrics
+                .iter()
+                .find(|(metric, _)| metric == name)
+                .and_then(|(_, value)| *value),
+        }
+    }
+
+    /// splits the filter into words, numbers, operators and parentheses
+    fn tokenize(s: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let mut token = String::from(c);
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                while let Some(next) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || *next == '_' || *next == '.') {
+                        break;
+                    }
+                    token.push(*next);
+                    chars.next();
+                }
+            } else if matches!(c, '<' | '>' | '=' | '!') {
+                if chars.peek() == Some(&'=') {
+                    token.push('=');
+                    chars.next();
+                }
+                if token == "=" || token == "!" {
+                    return Err(format!("unknown operator {token} in the filter: {s}"));
+                }
+            } else if !matches!(c, '(' | ')' | '-') {
+                return Err(format!("unexpected {c} in the filter: {s}"));
+            }
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    /// Recursive descent over the tokens: or binds loosest, then and, then not
+    struct Parser {
+        tokens: Vec<String>,
+        position: usize,
+    }
+
+    impl Parser {
+        fn next(&mut self) -> Option<String> {
+            let token = self.tokens.get(self.position).cloned();
+            self.position += 1;
+            token
+        }
+
+        fn next_is(&self, word: &str) -> bool {
+            self.tokens
+                .get(self.position)
+                .is_some_and(|token| token.eq_ignore_ascii_case(word))
+        }
+
+        fn or(&mut self) -> Result<Expression, String> {
+            let mut expression = self.and()?;
+            while self.next_is("or") {
+                self.position += 1;
+                expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+            }
+            Ok(expression)
+        }
+
+        fn and(&mut self) -> Result<Expression, String> {
+            let mut expression = self.not()?;
+            while self.next_is("and") {
+                self.position += 1;
+                expression = Expression::And(Box::new(expression), Box::new(self.not()?));
+            }
+            Ok(expression)
+        }
+
+        fn not(&mut self) -> Result<Expression, String> {
+            if self.next_is("not") {
+                self.position += 1;
+                return Ok(Expression::Not(Box::new(self.not()?)));
+            }
+            if self.next_is("(") {
+                self.position += 1;
+                let expression = self.or()?;
+                return match self.next().as_deref() {
+                    Some(")") => Ok(expression),
+                    _ => Err(String::from("missing ) in the filter")),
+                };
+            }
+            let left = self.operand()?;
+            let comparison = match self.next().as_deref() {
+                Some("<") => Comparison::Less,
+                Some("<=") => Comparison::LessOrEqual,
+                Some(">") => Comparison::Greater,
+                Some(">=") => Comparison::GreaterOrEqual,
+                Some("==") => Comparison::Equal,
+                Some("!=") => Comparison::NotEqual,
+                Some(token) => return Err(format!("expected a comparison, found {token}")),
+                None => return Err(String::from("the filter ends before a comparison")),
+            };
+            Ok(Expression::Compare(left, comparison, self.operand()?))
+        }
+
+        fn operand(&mut self) -> Result<Operand, String> {
+            let token = match self.next() {
+                Some(token) => token,
+                None => return Err(String::from("the filter ends before a value")),
+            };
+            if token == "-" {
+                return match self.operand()? {
+                    Operand::Number(number) => Ok(Operand::Number(-number)),
+                    Operand::Metric(name) => Err(format!("cannot negate {name}, only numbers")),
+                };
+            }
+            if let Ok(number) = token.parse::<f64>() {
+                return Ok(Operand::Number(number));
+            }
+            let is_name = token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && !["and", "or", "not"].contains(&token.to_lowercase().as_str());
+            match is_name {
+                true => Ok(Operand::Metric(token.to_lowercase())),
+                false => Err(format!("expected a metric or a number, found {token}")),
+            }
+        }
+    }
+
+    /// the metrics every symbol is screened on: the summary statistics, the last gain and close,
+    /// and the last value of each of the run's rolling statistics and indicators
+    pub(crate) fn metric_names(settings: &RunSettings) -> Vec<String> {
+        let mut names: Vec<String> = Summary::METRICS
+            .iter()
+            .map(|name| String::from(*name))
+            .collect();
+        names.extend([String::from("last_gain"), String::from("last_close")]);
+        names.extend(rolling_columns(&settings.rolling));
+        names.extend(indicator_columns(&settings.indicators));
+        names
+    }
+
+    /// the metrics of the symbol's rows, None when there are no rows
+    pub(crate) fn symbol_metrics(
+        symbol: &str,
+        rows: &[OutputRow],
+        settings: &RunSettings,
+    ) -> Option<Metrics> {
+        let gains: Vec<f64> = rows.iter().map(|row| row.gain).collect();
+        let summary = summarise(symbol, &gains, settings.interval.periods_per_year())?;
+        let last = rows.last()?;
+        let mut values: Vec<Option<f64>> = summary.values().into_iter().map(Some).collect();
+        values.extend([Some(last.gain), Some(last.quote.close)]);
+        values.extend(last.rolling.iter().chain(&last.indicators));
+        Some(metric_names(settings).into_iter().zip(values).collect())
+    }
+
+    /// writes the symbols that passed with their metrics to a csv file, by symbol
+    pub(crate) fn write_screen(
+        file_name: &Path,
+        names: &[String],
+        passed: &mut [(String, Metrics)],
+    ) -> Result<(), csv::Error> {
+        passed.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut writer = WriterBuilder::new()
+            .has_headers(false)
+            .from_path(file_name)?;
+        let mut header = vec![String::from("symbol")];
+        header.extend(names.iter().cloned());
+        writer.write_record(header)?;
+        for (symbol, metrics) in passed.iter() {
+            let mut cells = vec![Cell::Text(symbol.clone())];
+            cells.extend(metrics.iter().map(|(_, value)| match value {
+                Some(value) => Cell::Number(*value),
+                None => Cell::Text(String::new()),
+            }));
+            writer.serialize(cells)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+pub mod summary {
+    use std::path::Path;
+
+    use serde::Serialize;
+
+    /// Statistics over the gains of one symbol, one row of summary.csv
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    pub(crate) struct Summary {
+        pub symbol: String,
+        /// how many gains the statistics are over
+        pub count: usize,
+        pub mean: f64,
+        pub median: f64,
+        /// sample standard deviation of the gains
+        pub std_dev: f64,
+        /// standard deviation scaled up to a year
+        pub volatility: f64,
+        pub best: f64,
+        pub worst: f64,
+        /// share of the gains above 0, as a percent
+        pub up_percent: f64,
+        /// biggest fall from a peak of the compounded gains, as a factor of the peak
+        pub max_drawdown: f64,
+        /// annualised mean over standard deviation, with no risk-free rate
+        pub sharpe: f64,
+        /// annualised mean over downside deviation, with no risk-free rate
+        pub sortino: f64,
+    }
+
+    impl Summary {
+        /// the names of the statistics, the same as their summary.csv columns after the symbol
+        pub(crate) const METRICS: [&'static str; 11] = [
+            "count",
+            "mean",
+            "median",
+            "std_dev",
+            "volatility",
+            "best",
+            "worst",
+            "up_percent",
+            "max_drawdown",
+            "sharpe",
+            "sortino",
+        ];
+
+        /// the values of the statistics, in the same order as `METRICS`
+        pub(crate) fn values(&self) -> [f64; 11] {
+            [
+                self.count as f64,
+                self.mean,
+                self.median,
+                self.std_dev,
+                self.volatility,
+                self.best,
+                self.worst,
+                self.up_percent,
+                self.max_drawdown,
+                self.sharpe,
+                self.sortino,
+            ]
+        }
+    }
+
+    /// works out the statistics over the gains, None when there are no gains
+    ///
+    /// the ratios that would divide by zero are 0.0, like `get_gain`
+    pub(crate) fn summarise(symbol: &str, gains: &[f64], periods_per_year: f64) -> Option<Summary> {
+        if gains.is_empty() {
+            return None;
+        }
+
+        let count = gains.len();
+        let mean = gains.iter().sum::<f64>() / count as f64;
+        let mut sorted = gains.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        } else {
+            sorted[count / 2]
+        };
+        let std_dev = if count > 1 {
+            let squares: f64 = gains.iter().map(|gain| (gain - mean).powi(2)).sum();
+            (squares / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let downside_squares: f64 = gains.iter().map(|gain| gain.min(0.0).powi(2)).sum();
+        let downside_dev = (downside_squares / count as f64).sqrt();
+        let annualise = periods_per_year.sqrt();
+
+        Some(Summary {
+            symbol: String::from(symbol),
+            count,
+            mean,
+            median,
+            std_dev,
+            volatility: std_dev * annualise,
+            best: sorted[count - 1],
+            worst: sorted[0],
+            up_percent: gains.iter().filter(|gain| **gain > 0.0).count() as f64 * 100.0
+                / count as f64,
+            max_drawdown: max_drawdown(gains),
+            sharpe: ratio(mean, std_dev) * annualise,
+            sortino: ratio(mean, downside_dev) * annualise,
+        })
+    }
+
+    /// the biggest fall from a peak of the gains compounded from 1.0, 0.0 when they never fall
+    pub(crate) fn max_drawdown(gains: &[f64]) -> f64 {
+        let mut value = 1.0;
+        let mut peak = 1.0;
+        let mut drawdown: f64 = 0.0;
+        for gain in gains {
+            value *= 1.0 + gain;
+            peak = f64::max(peak, value);
+            if peak > 0.0 {
+                drawdown = drawdown.max((peak - value) / peak);
+            }
+        }
+        drawdown
+    }
+
+    /// one value over another, 0.0 when the other is 0.0
+    fn ratio(value: f64, over: f64) -> f64 {
+        if over == 0.0 {
+            return 0.0;
+        }
+
+        value / over
+    }
+
+    /// writes the summaries to a csv file under a header, by symbol
+    pub(crate) fn write_summary(
+        file_name: &Path,
+        summaries: &mut [Summary],
+    ) -> Result<(), csv::Error> {
+        summaries.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        let mut writer = csv::Writer::from_path(file_name)?;
+        for summary in summaries.iter() {
+            writer.serialize(summary)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -1,65 +1,453 @@
 pub mod symbol_processor {
-    use std::{fs::File, path::PathBuf, sync::mpsc, thread};
+    use std::{
+        collections::{BTreeMap, VecDeque},
+        fmt,
+        fs::{self, File, OpenOptions},
+        io::Write,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
 
-    use csv::Writer;
+    use csv::WriterBuilder;
+    use serde::Serialize;
+    use serde_json::{json, Map, Value};
     use tokio::runtime::Builder;
-    use yahoo_finance_api::{time::OffsetDateTime, Quote};
+    use yahoo_finance_api::{
+        time::{OffsetDateTime, UtcOffset},
+        Quote,
+    };
 
+    use crate::columnar::columnar::{record_batch, write_arrow_ipc, write_parquet};
+    #[cfg(feature = "postgres")]
+    use crate::config::config::PostgresConfig;
+    use crate::correlation::correlation::{build_statistic_matrix, GainSeries, Statistic};
+    use crate::database::database::write_sqlite;
+    use crate::date_range::date_range::DateRange;
+    use crate::incremental::incremental::{last_stored_date, resume_start};
+    use crate::indicators::indicators::{
+        indicator_columns, indicator_values, warmup_start, Indicator,
+    };
     use crate::log;
+    use crate::matrix::matrix::{build_matrix, write_matrix, FillPolicy};
+    #[cfg(feature = "postgres")]
+    use crate::postgres_sink::postgres_sink::write_postgres;
+    use crate::quote_provider::quote_provider::{Interval, QuoteHistory, QuoteProvider};
+    use crate::rolling::rolling::{rolling_columns, rolling_values};
+    use crate::screen::screen::{metric_names, symbol_metrics, write_screen, Expression, Metrics};
+    use crate::summary::summary::{summarise, write_summary, Summary};
 
-    /// using the list of symbols get the daily quotes for the past month
+    /// Which columns go into a symbol's output file
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum OutputMode {
+        /// date and gain
+        Gains,
+        /// date and the full quote: open, high, low, close, adjclose, volume
+        Ohlcv,
+        /// date, the full quote and the gain
+        OhlcvGains,
+    }
+
+    impl FromStr for OutputMode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "gains" => Ok(OutputMode::Gains),
+                "ohlcv" => Ok(OutputMode::Ohlcv),
+                "ohlcv+gains" => Ok(OutputMode::OhlcvGains),
+                _ => Err(format!(
+                    "unknown output mode: {s} (gains, ohlcv, ohlcv+gains)"
+                )),
+            }
+        }
+    }
+
+    /// How the gain of each quote is worked out
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Metric {
+        /// (close - open) / open
+        OpenClose,
+        /// close against the prior quote's close
+        CloseClose,
+        /// adjclose against the prior quote's adjclose
+        AdjClose,
+        /// natural log of close over the prior quote's close
+        Log,
+        /// open against the prior quote's close
+        Gap,
+        /// (high - low) / open
+        Range,
+        /// close against the prior quote's close, with dividends in between added back, the closes
+        /// being split-adjusted already like yahoo's
+        Total,
+    }
+
+    impl Metric {
+        /// the name the metric is picked by
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Metric::OpenClose => "open-close",
+                Metric::CloseClose => "close-close",
+                Metric::AdjClose => "adjclose",
+                Metric::Log => "log",
+                Metric::Gap => "gap",
+                Metric::Range => "range",
+                Metric::Total => "total",
+            }
+        }
+    }
+
+    impl fmt::Display for Metric {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.as_str())
+        }
+    }
+
+    impl FromStr for Metric {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "open-close" => Ok(Metric::OpenClose),
+                "close-close" => Ok(Metric::CloseClose),
+                "adjclose" => Ok(Metric::AdjClose),
+                "log" => Ok(Metric::Log),
+                "gap" => Ok(Metric::Gap),
+                "range" => Ok(Metric::Range),
+                "total" => Ok(Metric::Total),
+                _ => Err(format!(
+                    "unknown metric: {s} (open-close, close-close, adjclose, log, gap, range, total)"
+                )),
+            }
+        }
+    }
+
+    impl Metric {
+        /// whether the metric compares the quote with the one before it
+        pub(crate) fn looks_back(&self) -> bool {
+            !matches!(self, Metric::OpenClose | Metric::Range)
+        }
+
+        /// the gain of the quote, None for a metric that looks back when there is no prior quote
+        pub(crate) fn apply(
+            &self,
+            prior: Option<&Quote>,
+            quote: &Quote,
+            adjustment: Adjustment,
+        ) -> Option<f64> {
+            let gain = match (self, prior) {
+                (Metric::OpenClose, _) => get_gain(quote.clone()),
+                (Metric::Range, _) => ratio(quote.high - quote.low, quote.open),
+                (Metric::CloseClose, Some(prior)) => relative_change(prior.close, quote.close),
+                (Metric::AdjClose, Some(prior)) => relative_change(prior.adjclose, quote.adjclose),
+                (Metric::Log, Some(prior)) => log_change(prior.close, quote.close),
+                (Metric::Gap, Some(prior)) => relative_change(prior.close, quote.open),
+                (Metric::Total, Some(prior)) => {
+                    relative_change(prior.close, quote.close + adjustment.dividend)
+                }
+                (_, None) => return None,
+            };
+            Some(gain)
+        }
+    }
+
+    /// The dividends that went ex between a quote and the one before it, splits need no
+    /// adjusting as the closes are split-adjusted already
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub(crate) struct Adjustment {
+        /// cash paid per share, in the split-adjusted shares of the closes
+        pub dividend: f64,
+    }
+
+    impl Adjustment {
+        /// the dividends of the history after one timestamp and up to (and including) another
+        pub(crate) fn between(history: &QuoteHistory, after: u64, upto: u64) -> Self {
+            let mut adjustment = Adjustment::default();
+            let within = |timestamp: u64| timestamp > after && timestamp <= upto;
+            for dividend in history.dividends.iter().filter(|d| within(d.timestamp)) {
+                adjustment.dividend += dividend.amount;
+            }
+            adjustment
+        }
+    }
+
+    /// How each symbol's output file is written
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum OutputFormat {
+        /// a header row then one row per quote, in a file named after the symbol
+        Csv,
+        /// one SYMBOL.json document holding the symbol, range, interval, metric and rows
+        Json,
+        /// one json record per quote per line, in SYMBOL.jsonl
+        Jsonl,
+        /// every symbol's full quotes and gains in one ticker.parquet file
+        Parquet,
+        /// every symbol's full quotes and gains in one ticker.arrow (arrow ipc) file
+        Arrow,
+        /// every symbol's full quotes and gains added to the ticker.sqlite database
+        Sqlite,
+        /// every symbol's full quotes and gains copied into a postgres table, needs the postgres feature
+        Postgres,
+        /// every symbol's gains in one matrix.csv, a row per date and a column per symbol
+        Matrix,
+    }
+
+    impl FromStr for OutputFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "csv" => Ok(OutputFormat::Csv),
+                "json" => Ok(OutputFormat::Json),
+                "jsonl" => Ok(OutputFormat::Jsonl),
+                "parquet" => Ok(OutputFormat::Parquet),
+                "arrow" => Ok(OutputFormat::Arrow),
+                "sqlite" => Ok(OutputFormat::Sqlite),
+                "postgres" => Ok(OutputFormat::Postgres),
+                "matrix" => Ok(OutputFormat::Matrix),
+                _ => Err(format!(
+                    "unknown format: {s} (csv, json, jsonl, parquet, arrow, sqlite, postgres, matrix)"
+                )),
+            }
+        }
+    }
+
+    /// Everything about a run that every symbol is processed with
+    #[derive(Debug, Clone)]
+    pub struct RunSettings {
+        pub range: DateRange,
+        pub interval: Interval,
+        pub output_mode: OutputMode,
+        pub metric: Metric,
+        pub format: OutputFormat,
+        /// what the matrix format writes for a date a symbol has no quote for
+        pub fill: FillPolicy,
+        /// pick up after the rows already in each symbol's csv or jsonl file instead of writing it again
+        pub incremental: bool,
+        /// write statistics over each symbol's gains to summary.csv
+        pub summary: bool,
+        /// write the correlation and covariance of every pair of symbols' gains
+        pub correlate: bool,
+        /// technical indicators written as extra columns of each symbol's csv, json or jsonl file
+        pub indicators: Vec<Indicator>,
+        /// lengths of the windows the rolling statistics of the gain are written for
+        pub rolling: Vec<usize>,
+        /// only write the symbols whose metrics pass the filter, with their metrics, to screen.csv
+        pub screen: Option<Expression>,
+        /// the oldest quote the provider keeps at the interval, the warm-up is not fetched from
+        /// any earlier
+        pub earliest: Option<OffsetDateTime>,
+        /// where the postgres format writes to
+        #[cfg(feature = "postgres")]
+        pub postgres: PostgresConfig,
+    }
+
+    /// A single value in an output row, written without any tag
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    #[serde(untagged)]
+    pub(crate) enum Cell {
+        Text(String),
+        Number(f64),
+        Count(u64),
+    }
+
+    /// One quote of a symbol with the values worked out from it
+    #[derive(Debug, Clone)]
+    pub(crate) struct OutputRow {
+        pub date: String,
+        pub quote: Quote,
+        pub gain: f64,
+        /// the rolling statistics of the gain, in the same order as `rolling_columns`
+        pub rolling: Vec<Option<f64>>,
+        /// the values of the run's indicators, in the same order as `indicator_columns`
+        pub indicators: Vec<Option<f64>>,
+    }
+
+    impl OutputRow {
+        /// the column names written for the run's output mode, followed by the rolling statistics'
+        /// and the indicators' columns
+        pub(crate) fn columns(settings: &RunSettings) -> Vec<String> {
+            let mode = settings.output_mode;
+            let mut columns = vec!["date"];
+            if mode != OutputMode::Gains {
+                columns.extend(["open", "high", "low", "close", "adjclose", "volume"]);
+            }
+            if mode != OutputMode::Ohlcv {
+                columns.push("gain");
+            }
+            let mut columns: Vec<String> = columns.into_iter().map(String::from).collect();
+            columns.extend(rolling_columns(&settings.rolling));
+            columns.extend(indicator_columns(&settings.indicators));
+            columns
+        }
+
+        /// the values of the row, in the same order as `columns`
+        pub(crate) fn cells(&self, mode: OutputMode) -> Vec<Cell> {
+            let mut cells = vec![Cell::Text(self.date.clone())];
+            if mode != OutputMode::Gains {
+                cells.extend([
+                    Cell::Number(self.quote.open),
+                    Cell::Number(self.quote.high),
+                    Cell::Number(self.quote.low),
+                    Cell::Number(self.quote.close),
+                    Cell::Number(self.quote.adjclose),
+                    Cell::Count(self.quote.volume),
+                ]);
+            }
+            if mode != OutputMode::Ohlcv {
+                cells.push(Cell::Number(self.gain));
+            }
+            let optional = self.rolling.iter().chain(&self.indicators);
+            cells.extend(optional.map(|value| match value {
+                Some(value) => Cell::Number(*value),
+                None => Cell::Text(String::new()),
+            }));
+            cells
+        }
+
+        /// the row as a json object keyed by column name, rolling statistics and indicators without a
+        /// value are null
+        pub(crate) fn record(&self, settings: &RunSettings) -> Map<String, Value> {
+            let mut record = Map::new();
+            let columns = OutputRow::columns(settings);
+            for (column, cell) in columns.into_iter().zip(self.cells(settings.output_mode)) {
+                let value = match cell {
+                    Cell::Text(text) if text.is_empty() => Value::Null,
+                    cell => serde_json::to_value(cell).unwrap_or(Value::Null),
+                };
+                record.insert(column, value);
+            }
+            record
+        }
+    }
+
+    /// What every symbol of a run leaves behind to be written once they are all done
+    #[derive(Debug, Default)]
+    pub(crate) struct RunResults {
+        /// the rows, kept for the formats that write them all to one file
+        pub rows: Mutex<Vec<(String, OutputRow)>>,
+        /// the statistics over each symbol's gains, kept for summary.csv
+        pub summaries: Mutex<Vec<Summary>>,
+        /// each symbol's gains by date, kept for the correlation matrices
+        pub gains: Mutex<BTreeMap<String, GainSeries>>,
+        /// the symbols that passed the screen with their metrics
+        pub screened: Mutex<Vec<(String, Metrics)>>,
+    }
+
+    /// What one worker of the pool got done during a run
+    #[derive(Debug, Default)]
+    pub(crate) struct WorkerStats {
+        pub worker: usize,
+        pub symbols: usize,
+        pub busy: Duration,
+    }
+
+    impl WorkerStats {
+        /// the share of the run the worker spent processing symbols, 0.0 to 1.0
+        pub(crate) fn utilisation(&self, elapsed: Duration) -> f64 {
+            if elapsed.is_zero() {
+                return 0.0;
+            }
+
+            (self.busy.as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+        }
+    }
+
+    /// using the list of symbols get the quotes at the interval over the date range
+    ///
+    /// every symbol is fetched on one shared runtime through the one provider by a pool of
+    /// `threads` workers (one when not given) that each take the next symbol off a shared
+    /// queue as soon as they finish the last one
     pub fn process_symbols(
         symbols: Vec<&str>,
         output_dir: &PathBuf,
         threads: Option<u8>,
-        days_ago: Option<u32>,
+        settings: RunSettings,
+        provider: Arc<dyn QuoteProvider>,
     ) {
-        let one_day = time::Duration::days(1);
-        let one_month = time::Duration::days(30);
-        let mut the_day = OffsetDateTime::now_utc();
-        if days_ago != None {
-            let backup_days = time::Duration::days(days_ago.unwrap() as i64);
-            the_day -= backup_days;
-        }
-        let end_date = the_day - one_day;
-        let start_date = end_date - one_month;
-        let total_count = symbols.len();
-        let mut index: usize = 0;
+        let settings = Arc::new(settings);
+        let worker_count = threads.unwrap_or(1) as usize;
+        let queue: VecDeque<String> = symbols.into_iter().map(String::from).collect();
+        let queue = Arc::new(Mutex::new(queue));
+        let run_results = Arc::new(RunResults::default());
 
-        log("ticker", "begin");
+        let runtime = match Builder::new_multi_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                log("ticker", e);
+                return;
+            }
+        };
 
-        match threads {
-            Some(thread_count) => {
-                while index < total_count {
-                    let (tx, rx) = mpsc::channel();
-
-                    let start = index;
-                    let end = start + thread_count as usize;
-                    for x in start..end {
-                        if x < total_count {
-                            let symbol = String::from(symbols[x]);
-                            let out_dir = output_dir.clone();
-                            let tx_clone = tx.clone();
-                            thread::spawn(move || {
-                                process_one_symbol(&symbol, &out_dir, start_date, end_date);
-                                tx_clone.send(0).unwrap();
-                            });
-                        } else {
-                            break;
-                        }
-                    }
-                    thread::spawn(move || {
-                        tx.send(0).unwrap();
-                    });
+        log("ticker", "begin");
 
-                    for _received in rx {}
+        let run_start = Instant::now();
+        let all_stats = runtime.block_on(async {
+            let mut workers = Vec::new();
+            for worker in 0..worker_count {
+                workers.push(tokio::spawn(run_worker(
+                    worker,
+                    Arc::clone(&queue),
+                    Arc::clone(&provider),
+                    output_dir.clone(),
+                    Arc::clone(&settings),
+                    Arc::clone(&run_results),
+                )));
+            }
 
-                    index += thread_count as usize;
+            let mut all_stats = Vec::new();
+            for handle in workers {
+                match handle.await {
+                    Ok(stats) => all_stats.push(stats),
+                    Err(e) => log("ticker", e),
                 }
             }
-            None => {
-                for symbol in symbols {
-                    process_one_symbol(symbol, output_dir, start_date, end_date);
+            all_stats
+        });
+        let elapsed = run_start.elapsed();
+
+        for stats in all_stats {
+            let message = format!(
+                "worker {}: {} symbols, busy {:.1}s of {:.1}s ({:.0}%)",
+                stats.worker,
+                stats.symbols,
+                stats.busy.as_secs_f64(),
+                elapsed.as_secs_f64(),
+                stats.utilisation(elapsed) * 100.0
+            );
+            log("ticker", message);
+        }
+
+        // a screen collects no rows, writing the run-wide file would empty the one already there
+        if settings.screen.is_none() {
+            let mut run_rows = run_results.rows.lock().unwrap();
+            runtime.block_on(save_run_file(output_dir, &settings, &mut run_rows));
+        }
+        if settings.summary {
+            let mut summaries = run_results.summaries.lock().unwrap();
+            if let Err(e) = write_summary(&output_dir.join("summary.csv"), &mut summaries) {
+                log("ticker", e);
+            }
+        }
+        if settings.screen.is_some() {
+            let mut screened = run_results.screened.lock().unwrap();
+            log(
+                "ticker",
+                format!("{} symbols passed the screen", screened.len()),
+            );
+            let names = metric_names(&settings);
+            if let Err(e) = write_screen(&output_dir.join("screen.csv"), &names, &mut screened) {
+                log("ticker", e);
+            }
+        }
+        if settings.correlate {
+            let gains = run_results.gains.lock().unwrap();
+            for statistic in Statistic::ALL {
+                let matrix = build_statistic_matrix(&gains, statistic);
+                if let Err(e) = write_matrix(&output_dir.join(statistic.file_name()), matrix) {
+                    log("ticker", e);
                 }
             }
         }
@@ -67,34 +455,311 @@ pub mod symbol_processor {
         log("ticker", "end");
     }
 
+    /// One worker of the pool, processes symbols off the queue until it is empty
+    async fn run_worker(
+        worker: usize,
+        queue: Arc<Mutex<VecDeque<String>>>,
+        provider: Arc<dyn QuoteProvider>,
+        output_dir: PathBuf,
+        settings: Arc<RunSettings>,
+        run_results: Arc<RunResults>,
+    ) -> WorkerStats {
+        let mut stats = WorkerStats {
+            worker,
+            ..Default::default()
+        };
+        loop {
+            let next = queue.lock().unwrap().pop_front();
+            let symbol = match next {
+                Some(symbol) => symbol,
+                None => break,
+            };
+            let symbol_start = Instant::now();
+            process_one_symbol(
+                provider.as_ref(),
+                &symbol,
+                &output_dir,
+                &settings,
+                &run_results,
+            )
+            .await;
+            stats.busy += symbol_start.elapsed();
+            stats.symbols += 1;
+        }
+        stats
+    }
+
     /// Function that will process 1 symbol from http call to yahoo to save the file to local storage
-    fn process_one_symbol(
+    async fn process_one_symbol(
+        provider: &dyn QuoteProvider,
         symbol: &str,
         output_dir: &PathBuf,
-        start_date: OffsetDateTime,
-        end_date: OffsetDateTime,
+        settings: &RunSettings,
+        run_results: &RunResults,
     ) {
-        let quotes = get_quotes(symbol, start_date, end_date);
-        let mut gains = Vec::new();
-        for quote in quotes {
-            let gain = get_gain(quote);
-            gains.push(gain);
+        let mut range = settings.range;
+        let interval = settings.interval;
+        let last_date = match settings.incremental {
+            true => last_stored_date(output_dir, symbol, settings),
+            false => None,
+        };
+        if let Some(last_date) = &last_date {
+            let end_date = range.end.date().to_string();
+            if last_date.get(..10).unwrap_or(last_date) >= end_date.as_str() {
+                log(symbol, format!("Up to date: {last_date}"));
+                return;
+            }
+            match resume_start(last_date) {
+                Some(start) => range.start = range.start.max(start),
+                None => {
+                    log(symbol, format!("Cannot pick up after {last_date}"));
+                    return;
+                }
+            }
+        }
+        let warmup = settings
+            .indicators
+            .iter()
+            .map(Indicator::warmup)
+            .chain(settings.rolling.iter().copied())
+            // one quote before the range for a metric that looks back, unless picking up after
+            // a file whose last quote is fetched again to be the prior
+            .chain([(settings.metric.looks_back() && last_date.is_none()) as usize])
+            .max()
+            .unwrap_or(0);
+        let mut fetch_start = warmup_start(range.start, interval, warmup);
+        if let Some(earliest) = settings.earliest {
+            fetch_start = fetch_start.max(earliest);
+        }
+        let history = match get_quotes(provider, symbol, fetch_start, range.end, interval).await {
+            Some(history) => history,
+            None => return,
+        };
+        let indicator_rows = indicator_values(&settings.indicators, &history.quotes);
+        let mut rows: Vec<OutputRow> = Vec::new();
+        for (index, (quote, indicators)) in history.quotes.iter().zip(indicator_rows).enumerate() {
+            let prior = index.checked_sub(1).map(|prior| &history.quotes[prior]);
+            let adjustment = match prior {
+                Some(prior) => Adjustment::between(&history, prior.timestamp, quote.timestamp),
+                None => Adjustment::default(),
+            };
+            // the first quote fetched has no gain for a metric that looks back, it only serves
+            // as the prior of the next
+            let gain = match settings.metric.apply(prior, quote, adjustment) {
+                Some(gain) => gain,
+                None => continue,
+            };
+            rows.push(OutputRow {
+                date: format_timestamp(quote.timestamp, history.utc_offset, interval),
+                quote: quote.clone(),
+                gain,
+                rolling: Vec::new(),
+                indicators,
+            });
+        }
+        if !settings.rolling.is_empty() {
+            let gains: Vec<f64> = rows.iter().map(|row| row.gain).collect();
+            let rolling_rows = rolling_values(&settings.rolling, &gains);
+            for (row, rolling) in rows.iter_mut().zip(rolling_rows) {
+                row.rolling = rolling;
+            }
+        }
+        let start = range.start.unix_timestamp().max(0) as u64;
+        if warmup > 0 {
+            rows.retain(|row| row.quote.timestamp >= start);
+        }
+        if let Some(last_date) = &last_date {
+            rows.retain(|row| row.date > *last_date);
         }
-        save_gains(output_dir, symbol, gains);
+        if settings.summary {
+            let gains: Vec<f64> = rows.iter().map(|row| row.gain).collect();
+            if let Some(summary) = summarise(symbol, &gains, interval.periods_per_year()) {
+                run_results.summaries.lock().unwrap().push(summary);
+            }
+        }
+        if settings.correlate {
+            let gains: GainSeries = rows
+                .iter()
+                .map(|row| (row.date.clone(), row.gain))
+                .collect();
+            run_results
+                .gains
+                .lock()
+                .unwrap()
+                .insert(String::from(symbol), gains);
+        }
+        if let Some(screen) = &settings.screen {
+            match symbol_metrics(symbol, &rows, settings) {
+                Some(metrics) if screen.matches(&metrics) => {
+                    run_results
+                        .screened
+                        .lock()
+                        .unwrap()
+                        .push((String::from(symbol), metrics));
+                }
+                Some(_) => (),
+                None => log(symbol, "no quotes to screen"),
+            }
+            return;
+        }
+        save_gains(
+            output_dir,
+            symbol,
+            rows,
+            settings,
+            &run_results.rows,
+            last_date.is_some(),
+        );
+        // the run-wide formats have no per-symbol file for the events to go next to
+        let per_symbol = matches!(
+            settings.format,
+            OutputFormat::Csv | OutputFormat::Json | OutputFormat::Jsonl
+        );
+        let events = event_rows(&history, interval, start);
+        if per_symbol && !events.is_empty() {
+            save_events(output_dir, symbol, events, last_date.is_some());
+        }
+    }
+
+    /// saves the rows to the output directory in the run's format, the run-wide formats keep them
+    /// in `run_rows` until every symbol is done, csv and jsonl rows can be added to the existing file
+    fn save_gains(
+        output_dir: &Path,
+        symbol: &str,
+        rows: Vec<OutputRow>,
+        settings: &RunSettings,
+        run_rows: &Mutex<Vec<(String, OutputRow)>>,
+        append: bool,
+    ) {
+        match settings.format {
+            OutputFormat::Parquet
+            | OutputFormat::Arrow
+            | OutputFormat::Sqlite
+            | OutputFormat::Postgres
+            | OutputFormat::Matrix => {
+                let mut run_rows = run_rows.lock().unwrap();
+                run_rows.extend(rows.into_iter().map(|row| (String::from(symbol), row)));
+            }
+            OutputFormat::Csv => save_csv(output_dir, symbol, rows, settings, append),
+            OutputFormat::Json => {
+                let document = json_document(symbol, &rows, settings);
+                match serde_json::to_string_pretty(&document) {
+                    Err(e) => log(symbol, e),
+                    Ok(json) => {
+                        let file_name = output_dir.join(format!("{symbol}.json"));
+                        if let Err(e) = fs::write(file_name, json) {
+                            log(symbol, e);
+                        }
+                    }
+                }
+            }
+            OutputFormat::Jsonl => {
+                let mut lines = String::new();
+                for row in rows {
+                    let mut record = Map::new();
+                    record.insert(String::from("symbol"), Value::from(symbol));
+                    record.extend(row.record(settings));
+                    match serde_json::to_string(&record) {
+                        Err(e) => log(symbol, e),
+                        Ok(line) => {
+                            lines.push_str(&line);
+                            lines.push('\n');
+                        }
+                    }
+                }
+                let file_name = output_dir.join(format!("{symbol}.jsonl"));
+                let result = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(file_name)
+                    .and_then(|mut file| file.write_all(lines.as_bytes()));
+                if let Err(e) = result {
+                    log(symbol, e);
+                }
+            }
+        }
+    }
+
+    /// saves the rows of every symbol to the one run-wide file or database, by symbol then date
+    async fn save_run_file(
+        output_dir: &Path,
+        settings: &RunSettings,
+        run_rows: &mut [(String, OutputRow)],
+    ) {
+        run_rows.sort_by(|a, b| a.0.cmp(&b.0));
+        let result = match settings.format {
+            OutputFormat::Parquet => record_batch(run_rows)
+                .and_then(|batch| write_parquet(&output_dir.join("ticker.parquet"), &batch)),
+            OutputFormat::Arrow => record_batch(run_rows)
+                .and_then(|batch| write_arrow_ipc(&output_dir.join("ticker.arrow"), &batch)),
+            OutputFormat::Sqlite => {
+                write_sqlite(&output_dir.join("ticker.sqlite"), run_rows, settings)
+                    .map(|run_id| log("ticker", format!("saved as run {run_id}")))
+                    .map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "postgres")]
+            OutputFormat::Postgres => write_postgres(&settings.postgres, run_rows, settings)
+                .await
+                .map(|written| log("ticker", format!("wrote {written} bars to postgres")))
+                .map_err(|e| e.to_string()),
+            OutputFormat::Matrix => write_matrix(
+                &output_dir.join("matrix.csv"),
+                build_matrix(run_rows, settings.fill),
+            )
+            .map_err(|e| e.to_string()),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            log("ticker", e);
+        }
+    }
+
+    /// the json document for a symbol: the symbol, the range, the interval, the metric and the rows
+    pub(crate) fn json_document(symbol: &str, rows: &[OutputRow], settings: &RunSettings) -> Value {
+        let rows: Vec<Value> = rows
+            .iter()
+            .map(|row| Value::Object(row.record(settings)))
+            .collect();
+        json!({
+            "symbol": symbol,
+            "start": settings.range.start.date().to_string(),
+            "end": settings.range.end.date().to_string(),
+            "interval": settings.interval.as_str(),
+            "metric": settings.metric.as_str(),
+            "rows": rows,
+        })
     }
 
-    /// saves the daily gains to a csv file in the output directory
-    fn save_gains(output_dir: &PathBuf, symbol: &str, gains: Vec<f64>) {
+    /// saves the rows to a csv file in the output directory, one dated row per quote under a header,
+    /// or after the rows already in the file when appending
+    fn save_csv(
+        output_dir: &Path,
+        symbol: &str,
+        rows: Vec<OutputRow>,
+        settings: &RunSettings,
+        append: bool,
+    ) {
+        let mode = settings.output_mode;
         let file_name = output_dir.join(symbol);
-        let file_result = File::create(file_name);
+        let file_result = match append {
+            true => OpenOptions::new().append(true).open(file_name),
+            false => File::create(file_name),
+        };
         match file_result {
             Err(e) => log(symbol, e),
             Ok(file) => {
-                let mut writer = Writer::from_writer(file);
-                let serialize_result = writer.serialize(gains);
-                match serialize_result {
-                    Err(e) => log(symbol, e),
-                    Ok(_) => (),
+                let mut writer = WriterBuilder::new().has_headers(false).from_writer(file);
+                if !append {
+                    if let Err(e) = writer.write_record(OutputRow::columns(settings)) {
+                        log(symbol, e);
+                    }
+                }
+                for row in rows {
+                    if let Err(e) = writer.serialize(row.cells(mode)) {
+                        log(symbol, e);
+                    }
                 }
                 let flush_result = writer.flush();
                 match flush_result {
@@ -105,44 +770,163 @@ pub mod symbol_processor {
         }
     }
 
+    /// the splits and dividends of the history from the start on as date, event, value rows in
+    /// date order, leaving out those of the quotes fetched before the range to warm up
+    pub(crate) fn event_rows(
+        history: &QuoteHistory,
+        interval: Interval,
+        start: u64,
+    ) -> Vec<Vec<Cell>> {
+        let mut events = Vec::new();
+        for split in &history.splits {
+            let ratio = format!("{}:{}", split.numerator, split.denominator);
+            events.push((split.timestamp, "split", Cell::Text(ratio)));
+        }
+        for dividend in &history.dividends {
+            events.push((
+                dividend.timestamp,
+                "dividend",
+                Cell::Number(dividend.amount),
+            ));
+        }
+        events.retain(|(timestamp, _, _)| *timestamp >= start);
+        events.sort_by_key(|(timestamp, _, _)| *timestamp);
+        events
+            .into_iter()
+            .map(|(timestamp, event, value)| {
+                let date = format_timestamp(timestamp, history.utc_offset, interval);
+                vec![Cell::Text(date), Cell::Text(String::from(event)), value]
+            })
+            .collect()
+    }
+
+    /// saves the event rows to SYMBOL.events in the output directory under a date,event,value
+    /// header, or when appending adds the ones after the last stored event to its end
+    fn save_events(output_dir: &Path, symbol: &str, events: Vec<Vec<Cell>>, append: bool) {
+        let file_name = output_dir.join(format!("{symbol}.events"));
+        let last_date = match append {
+            true => fs::read_to_string(&file_name).ok().and_then(|contents| {
+                let lines = contents.lines().filter(|line| !line.trim().is_empty());
+                let last = lines.skip(1).last()?;
+                last.split(',').next().map(String::from)
+            }),
+            false => None,
+        };
+        let events: Vec<Vec<Cell>> = events
+            .into_iter()
+            .filter(|event| match (&last_date, event.first()) {
+                (Some(last_date), Some(Cell::Text(date))) => date > last_date,
+                _ => true,
+            })
+            .collect();
+        if events.is_empty() {
+            return;
+        }
+        let file_result = match last_date {
+            Some(_) => OpenOptions::new().append(true).open(file_name),
+            None => File::create(file_name),
+        };
+        let mut writer = match file_result {
+            Ok(file) => WriterBuilder::new().has_headers(false).from_writer(file),
+            Err(e) => {
+                log(symbol, e);
+                return;
+            }
+        };
+        if last_date.is_none() {
+            if let Err(e) = writer.write_record(["date", "event", "value"]) {
+                log(symbol, e);
+            }
+        }
+        for event in events {
+            if let Err(e) = writer.serialize(event) {
+                log(symbol, e);
+            }
+        }
+        if let Err(e) = writer.flush() {
+            log(symbol, e);
+        }
+    }
+
+    /// turns a quote's timestamp into the exchange-local ISO date, with the time of day for intraday intervals
+    pub(crate) fn format_timestamp(timestamp: u64, utc_offset: i32, interval: Interval) -> String {
+        let offset = UtcOffset::from_whole_seconds(utc_offset).unwrap_or(UtcOffset::UTC);
+        let local = match OffsetDateTime::from_unix_timestamp(timestamp as i64) {
+            Ok(utc) => utc.to_offset(offset),
+            Err(_) => return timestamp.to_string(),
+        };
+        let date = format!(
+            "{:04}-{:02}-{:02}",
+            local.year(),
+            local.month() as u8,
+            local.day()
+        );
+        if !interval.is_intraday() {
+            return date;
+        }
+
+        let (hours, minutes, _) = offset.as_hms();
+        format!(
+            "{}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+            date,
+            local.hour(),
+            local.minute(),
+            local.second(),
+            if offset.is_negative() { '-' } else { '+' },
+            hours.abs(),
+            minutes.abs()
+        )
+    }
+
     /// converts Quote to the single value of the gain of the day (+/-) as a factor of the overall starting quote
     pub(crate) fn get_gain(quote: Quote) -> f64 {
-        if quote.open == 0.0 {
+        relative_change(quote.open, quote.close)
+    }
+
+    /// the change from one value to another as a factor of the first, 0.0 when the first is 0.0
+    pub(crate) fn relative_change(from: f64, to: f64) -> f64 {
+        ratio(to - from, from)
+    }
+
+    /// one value over another, 0.0 when the other is 0.0
+    fn ratio(value: f64, over: f64) -> f64 {
+        if over == 0.0 {
             return 0.0;
         }
 
-        (quote.close - quote.open) / quote.open
+        value / over
     }
 
-    /// Method to get that quotes over a duration for a given ticker symbol
-    fn get_quotes(symbol: &str, start: OffsetDateTime, end: OffsetDateTime) -> Vec<Quote> {
-        let quotes = Vec::new();
-        let provider_result = yahoo_finance_api::YahooConnector::new();
-        match provider_result {
-            Err(e) => log(symbol, e),
-            Ok(provider) => {
-                let builder_result = Builder::new_current_thread().enable_all().build();
-                match builder_result {
-                    Err(e) => log(symbol, e),
-                    Ok(builder) => {
-                        let resp_result =
-                            builder.block_on(provider.get_quote_history(symbol, start, end));
-                        match resp_result {
-                            Err(e) => log(symbol, e),
-                            Ok(resp) => match resp.quotes() {
-                                Err(e) => log(symbol, e),
-                                Ok(response) => {
-                                    let message =
-                                        format!("Success: {} - {}", start.date(), end.date(),);
-                                    log(symbol, message);
-                                    return response;
-                                }
-                            },
-                        }
-                    }
-                }
+    /// the natural log of one value over another, 0.0 when either is not above 0.0
+    pub(crate) fn log_change(from: f64, to: f64) -> f64 {
+        if from <= 0.0 || to <= 0.0 {
+            return 0.0;
+        }
+
+        (to / from).ln()
+    }
+
+    /// Method to get that quotes over a duration for a given ticker symbol, None when the provider gave up
+    async fn get_quotes(
+        provider: &dyn QuoteProvider,
+        symbol: &str,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+        interval: Interval,
+    ) -> Option<QuoteHistory> {
+        match provider
+            .get_quote_history(symbol, start, end, interval)
+            .await
+        {
+            Err(e) => {
+                log(symbol, format!("Failed, no output written: {e}"));
+                None
+            }
+            Ok(response) => {
+                let message = format!("Success: {} - {} at {}", start.date(), end.date(), interval);
+                log(symbol, message);
+                Some(response)
             }
         }
-        quotes
     }
 }
@@ -1,12 +1,61 @@
 /// Tests
 #[cfg(test)]
 mod unit_tests {
-    use std::{fs, path::PathBuf};
-
-    use yahoo_finance_api::Quote;
-
-    use crate::symbol_processor::symbol_processor::get_gain;
-    use crate::{get_ticker_symbols, read_file, validate_args};
+    use std::{
+        collections::BTreeMap,
+        fs,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use arrow_array::{RecordBatch, StringArray};
+    use arrow_ipc::reader::FileReader;
+    use async_trait::async_trait;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use tokio::runtime::Builder;
+    use yahoo_finance_api::{
+        time::{self, Date, Month, OffsetDateTime},
+        Quote,
+    };
+
+    use crate::cache::cache::{cache_source, evict, CacheStats, CachingProvider, ResponseCache};
+    use crate::columnar::columnar::record_batch;
+    use crate::config::config::{read_config, PostgresConfig, RateLimitConfig};
+    use crate::correlation::correlation::{
+        aligned, build_statistic_matrix, covariance, pearson, ranks, spearman, GainSeries,
+        Statistic,
+    };
+    use crate::database::database::write_sqlite;
+    use crate::date_range::date_range::{parse_date, parse_window, DateRange};
+    use crate::incremental::incremental::{last_stored_date, resume_start};
+    use crate::indicators::indicators::{indicator_columns, warmup_start, Indicator};
+    use crate::matrix::matrix::{build_matrix, FillPolicy};
+    #[cfg(feature = "postgres")]
+    use crate::postgres_sink::postgres_sink::{table_name, upsert_sql, write_postgres};
+    use crate::quote_provider::quote_provider::{
+        DividendEvent, ErrorClass, FileProvider, Interval, ProviderError, ProviderKind,
+        QuoteHistory, QuoteProvider, SplitEvent,
+    };
+    use crate::rate_limit::rate_limit::{RateLimitedProvider, TokenBucket};
+    use crate::retry::retry::{RetryPolicy, RetryingProvider};
+    use crate::rolling::rolling::{rolling_columns, rolling_values};
+    use crate::screen::screen::{metric_names, symbol_metrics, Comparison, Expression, Operand};
+    use crate::summary::summary::{max_drawdown, summarise};
+    use crate::symbol_processor::symbol_processor::{
+        event_rows, format_timestamp, get_gain, json_document, process_symbols, Adjustment, Cell,
+        Metric, OutputFormat, OutputMode, OutputRow, RunSettings, WorkerStats,
+    };
+    use crate::vcr::vcr::{Fixture, RecordingProvider, ReplayProvider};
+    use crate::{
+        get_ticker_symbols, read_file, validate_args, validate_cache_dir, validate_incremental,
+        validate_indicators, validate_lookback, validate_postgres, validate_provider_args,
+        validate_range, validate_rate_limit, validate_retry_args, validate_rolling,
+        validate_screen,
+    };
 
     fn read_test_data() -> String {
         let read_result = fs::read_to_string("testdata.txt");
@@ -16,6 +65,109 @@ mod unit_tests {
         };
     }
 
+    /// provider that hands back the same quotes for every symbol except FAIL
+    struct FakeProvider {
+        quotes: Vec<Quote>,
+    }
+
+    #[async_trait]
+    impl QuoteProvider for FakeProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            _start: OffsetDateTime,
+            _end: OffsetDateTime,
+            _interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            if symbol == "FAIL" {
+                return Err(ProviderError::Fetch(String::from("429 Too Many Requests")));
+            }
+            Ok(QuoteHistory::new(self.quotes.clone()))
+        }
+    }
+
+    /// provider that fails with the given error until it has been asked `failures` times
+    struct FlakyProvider {
+        failures: u32,
+        status: &'static str,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl QuoteProvider for FlakyProvider {
+        async fn get_quote_history(
+            &self,
+            _symbol: &str,
+            _start: OffsetDateTime,
+            _end: OffsetDateTime,
+            _interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if call <= self.failures {
+                return Err(ProviderError::Fetch(String::from(self.status)));
+            }
+            Ok(QuoteHistory::new(vec![fake_quote(1, 1.0, 2.0)]))
+        }
+    }
+
+    fn quick_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            jitter: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn fake_quote(timestamp: u64, open: f64, close: f64) -> Quote {
+        Quote {
+            timestamp,
+            open,
+            high: 0.0,
+            low: 0.0,
+            volume: 0,
+            close,
+            adjclose: 0.0,
+        }
+    }
+
+    /// daily quotes over the default 30 day range ending yesterday
+    fn test_settings(output_mode: OutputMode) -> RunSettings {
+        RunSettings {
+            range: DateRange::resolve(None, None, None, None, OffsetDateTime::now_utc()),
+            interval: Interval::OneDay,
+            output_mode,
+            metric: Metric::OpenClose,
+            format: OutputFormat::Csv,
+            fill: FillPolicy::Empty,
+            incremental: false,
+            summary: false,
+            correlate: false,
+            indicators: Vec::new(),
+            rolling: Vec::new(),
+            screen: None,
+            earliest: None,
+            #[cfg(feature = "postgres")]
+            postgres: PostgresConfig::default(),
+        }
+    }
+
+    fn make_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ticker_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// runs a provider call to completion for tests that call a provider directly
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
     fn vectors_are_equal(v1: Vec<&str>, v2: Vec<&str>) -> bool {
         if v1.iter().count() != v2.iter().count() {
             println!(
@@ -149,7 +301,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(true);
@@ -164,7 +316,7 @@ mod unit_tests {
         let threads = Some(10);
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(true);
@@ -179,7 +331,7 @@ mod unit_tests {
         let threads = Some(3);
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(true);
@@ -194,7 +346,7 @@ mod unit_tests {
         let threads = Some(2);
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(true);
@@ -210,7 +362,7 @@ mod unit_tests {
         let threads = Some(1);
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -226,7 +378,7 @@ mod unit_tests {
         let threads = Some(0);
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -242,7 +394,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false)
@@ -258,7 +410,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -274,7 +426,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -290,7 +442,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -306,7 +458,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -321,7 +473,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(true);
@@ -337,7 +489,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -353,7 +505,7 @@ mod unit_tests {
         let threads = None;
 
         // act
-        validate_args(&file_name, &dir, &log, threads);
+        validate_args(&file_name, &dir, &log, threads, None, None);
 
         // assert
         assert!(false);
@@ -412,4 +564,3495 @@ mod unit_tests {
         // assert
         assert!(vectors_are_equal(expected, actual));
     }
+
+    #[test]
+    fn provider_kind_yahoo() {
+        // assign
+        let text = "Yahoo";
+
+        // act
+        let actual = text.parse::<ProviderKind>();
+
+        // assert
+        assert_eq!(Ok(ProviderKind::Yahoo), actual);
+    }
+
+    #[test]
+    fn provider_kind_unknown() {
+        // assign
+        let text = "nasdaq";
+
+        // act
+        let actual = text.parse::<ProviderKind>();
+
+        // assert
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn process_symbols_with_fake_provider() {
+        // assign
+        let dir = make_test_dir("fake_provider");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86_400, 2.0, 1.0)],
+        });
+        let symbols = vec!["AAPL", "MSFT"];
+
+        // act
+        process_symbols(
+            symbols,
+            &dir,
+            None,
+            test_settings(OutputMode::Gains),
+            provider,
+        );
+
+        // assert
+        let actual = fs::read_to_string(dir.join("AAPL")).unwrap();
+        assert_eq!("date,gain\n1970-01-01,1.0\n1970-01-02,-0.5\n", actual);
+        assert!(dir.join("MSFT").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_with_fake_provider_threaded() {
+        // assign
+        let dir = make_test_dir("fake_provider_threaded");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(1, 1.0, 2.0)],
+        });
+        let symbols = vec!["AAPL", "MSFT", "FAIL"];
+
+        // act
+        process_symbols(
+            symbols,
+            &dir,
+            Some(2),
+            test_settings(OutputMode::Gains),
+            provider,
+        );
+
+        // assert
+        let expected = "date,gain\n1970-01-01,1.0\n";
+        assert_eq!(expected, fs::read_to_string(dir.join("AAPL")).unwrap());
+        assert_eq!(expected, fs::read_to_string(dir.join("MSFT")).unwrap());
+        assert!(!dir.join("FAIL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_provider_reads_csv_in_range() {
+        // assign
+        let dir = make_test_dir("file_provider_csv");
+        fs::write(
+            dir.join("AAPL.csv"),
+            "timestamp,open,high,low,close,volume,adjclose\n\
+             100,1.0,2.0,0.5,1.5,10,1.5\n\
+             200,1.5,2.5,1.0,2.0,20,2.0\n\
+             300,2.0,3.0,1.5,2.5,30,2.5\n",
+        )
+        .unwrap();
+        let provider = FileProvider::new(dir.clone());
+        let start = OffsetDateTime::from_unix_timestamp(150).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(300).unwrap();
+
+        // act
+        let actual =
+            block_on(provider.get_quote_history("AAPL", start, end, Interval::OneDay)).unwrap();
+
+        // assert
+        assert_eq!(
+            vec![200, 300],
+            actual
+                .quotes
+                .iter()
+                .map(|q| q.timestamp)
+                .collect::<Vec<u64>>()
+        );
+        assert_eq!(20, actual.quotes[0].volume);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_provider_reads_json() {
+        // assign
+        let dir = make_test_dir("file_provider_json");
+        fs::write(
+            dir.join("MSFT.json"),
+            r#"[{"timestamp":100,"open":1.0,"high":2.0,"low":0.5,"volume":10,"close":1.5,"adjclose":1.4}]"#,
+        )
+        .unwrap();
+        let provider = FileProvider::new(dir.clone());
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual =
+            block_on(provider.get_quote_history("MSFT", start, end, Interval::OneDay)).unwrap();
+
+        // assert
+        assert_eq!(1, actual.quotes.len());
+        assert_eq!(1.4, actual.quotes[0].adjclose);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_provider_missing_symbol() {
+        // assign
+        let dir = make_test_dir("file_provider_missing");
+        let provider = FileProvider::new(dir.clone());
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual = block_on(provider.get_quote_history("NOPE", start, end, Interval::OneDay));
+
+        // assert
+        assert!(matches!(actual, Err(ProviderError::NotFound(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "data_dir is needed for the file provider")]
+    fn validate_provider_args_file_without_data_dir() {
+        // assign
+        let data_dir = None;
+
+        // act
+        validate_provider_args(ProviderKind::File, &data_dir, &None, &None);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "data directory does not exist")]
+    fn validate_provider_args_file_with_missing_data_dir() {
+        // assign
+        let data_dir = Some(PathBuf::from("baddirectory"));
+
+        // act
+        validate_provider_args(ProviderKind::File, &data_dir, &None, &None);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn record_then_replay_gives_same_output() {
+        // assign
+        let fixtures = make_test_dir("vcr_fixtures");
+        let recorded_out = make_test_dir("vcr_recorded");
+        let replayed_out = make_test_dir("vcr_replayed");
+        let fake = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(1, 1.0, 2.0), fake_quote(2, 2.0, 1.0)],
+        });
+        let recorder = Arc::new(RecordingProvider::new(fake, fixtures.clone()));
+        process_symbols(
+            vec!["AAPL", "FAIL"],
+            &recorded_out,
+            None,
+            test_settings(OutputMode::Gains),
+            recorder,
+        );
+        let replayer = Arc::new(ReplayProvider::new(fixtures.clone()));
+
+        // act
+        process_symbols(
+            vec!["AAPL", "FAIL"],
+            &replayed_out,
+            Some(2),
+            test_settings(OutputMode::Gains),
+            replayer,
+        );
+
+        // assert
+        assert!(fixtures.join("AAPL.json").exists());
+        assert!(fixtures.join("FAIL.json").exists());
+        assert_eq!(
+            fs::read_to_string(recorded_out.join("AAPL")).unwrap(),
+            fs::read_to_string(replayed_out.join("AAPL")).unwrap()
+        );
+        assert!(!replayed_out.join("FAIL").exists());
+        for dir in [fixtures, recorded_out, replayed_out] {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn replay_missing_fixture() {
+        // assign
+        let fixtures = make_test_dir("vcr_missing");
+        let replayer = ReplayProvider::new(fixtures.clone());
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual = block_on(replayer.get_quote_history("AAPL", start, end, Interval::OneDay));
+
+        // assert
+        assert!(matches!(actual, Err(ProviderError::NotFound(_))));
+        fs::remove_dir_all(&fixtures).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "replay directory does not exist")]
+    fn validate_provider_args_missing_replay_dir() {
+        // assign
+        let replay = Some(PathBuf::from("baddirectory"));
+
+        // act
+        validate_provider_args(ProviderKind::Yahoo, &None, &None, &replay);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn worker_stats_utilisation_half_busy() {
+        // assign
+        let stats = WorkerStats {
+            worker: 0,
+            symbols: 3,
+            busy: Duration::from_secs(2),
+        };
+
+        // act
+        let actual = stats.utilisation(Duration::from_secs(4));
+
+        // assert
+        assert_eq!(0.5, actual);
+    }
+
+    #[test]
+    fn worker_stats_utilisation_no_time_elapsed() {
+        // assign
+        let stats = WorkerStats::default();
+
+        // act
+        let actual = stats.utilisation(Duration::ZERO);
+
+        // assert
+        assert_eq!(0.0, actual);
+    }
+
+    #[test]
+    fn process_symbols_more_symbols_than_workers() {
+        // assign
+        let dir = make_test_dir("worker_pool");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(1, 2.0, 1.0)],
+        });
+        let symbols = vec!["A", "B", "C", "D", "E", "F", "G"];
+
+        // act
+        process_symbols(
+            symbols.clone(),
+            &dir,
+            Some(3),
+            test_settings(OutputMode::Gains),
+            provider,
+        );
+
+        // assert
+        for symbol in symbols {
+            assert_eq!(
+                "date,gain\n1970-01-01,-0.5\n",
+                fs::read_to_string(dir.join(symbol)).unwrap()
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retry_succeeds_after_throttling() {
+        // assign
+        let flaky = Arc::new(FlakyProvider {
+            failures: 2,
+            status: "429 Too Many Requests",
+            calls: AtomicU32::new(0),
+        });
+        let provider = RetryingProvider::new(flaky.clone(), quick_policy(3));
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual = block_on(provider.get_quote_history("AAPL", start, end, Interval::OneDay));
+
+        // assert
+        assert!(actual.is_ok());
+        assert_eq!(3, flaky.calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn retry_gives_up_after_max_attempts() {
+        // assign
+        let flaky = Arc::new(FlakyProvider {
+            failures: 5,
+            status: "503 Service Unavailable",
+            calls: AtomicU32::new(0),
+        });
+        let provider = RetryingProvider::new(flaky.clone(), quick_policy(2));
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual = block_on(provider.get_quote_history("AAPL", start, end, Interval::OneDay));
+
+        // assert
+        assert!(matches!(actual, Err(ProviderError::Fetch(_))));
+        assert_eq!(2, flaky.calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn retry_skips_errors_that_are_not_retryable() {
+        // assign
+        let flaky = Arc::new(FlakyProvider {
+            failures: 5,
+            status: "404 Not Found",
+            calls: AtomicU32::new(0),
+        });
+        let provider = RetryingProvider::new(flaky.clone(), quick_policy(3));
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual = block_on(provider.get_quote_history("AAPL", start, end, Interval::OneDay));
+
+        // assert
+        assert!(actual.is_err());
+        assert_eq!(1, flaky.calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn retry_backoff_doubles() {
+        // assign
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            ..Default::default()
+        };
+
+        // act
+        let actual: Vec<Duration> = (2..=5).map(|attempt| policy.backoff(attempt)).collect();
+
+        // assert
+        assert_eq!(
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                Duration::from_millis(800)
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn retry_delay_stays_within_jitter() {
+        // assign
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            jitter: 0.5,
+            ..Default::default()
+        };
+
+        // act
+        let actual = policy.delay(2);
+
+        // assert
+        assert!(actual >= Duration::from_millis(50) && actual <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn provider_error_classes() {
+        // assign
+        let errors = [
+            ProviderError::Fetch(String::from("429 Too Many Requests")),
+            ProviderError::Fetch(String::from("502 Bad Gateway")),
+            ProviderError::Fetch(String::from("404 Not Found")),
+            ProviderError::Connection(String::from("timed out")),
+        ];
+
+        // act
+        let actual: Vec<ErrorClass> = errors.iter().map(|e| e.class()).collect();
+
+        // assert
+        assert_eq!(
+            vec![
+                ErrorClass::Throttled,
+                ErrorClass::Server,
+                ErrorClass::Client,
+                ErrorClass::Connection
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "retry_jitter needs to be between 0.0 and 1.0")]
+    fn validate_retry_args_jitter_too_big() {
+        // assign
+        let jitter = 1.5;
+
+        // act
+        validate_retry_args(3, jitter);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_attempts needs to be at least 1")]
+    fn validate_retry_args_no_attempts() {
+        // assign
+        let max_attempts = 0;
+
+        // act
+        validate_retry_args(max_attempts, 0.5);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn token_bucket_starts_full() {
+        // assign
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, Duration::from_secs(1), now);
+
+        // act
+        let first = bucket.wait_time(now);
+        bucket.take();
+        let second = bucket.wait_time(now);
+        bucket.take();
+        let third = bucket.wait_time(now);
+
+        // assert
+        assert_eq!(Duration::ZERO, first);
+        assert_eq!(Duration::ZERO, second);
+        assert_eq!(Duration::from_millis(500), third);
+    }
+
+    #[test]
+    fn token_bucket_refills_over_time() {
+        // assign
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(60.0, Duration::from_secs(60), now);
+        for _ in 0..60 {
+            bucket.take();
+        }
+
+        // act
+        let actual = bucket.wait_time(now + Duration::from_secs(1));
+
+        // assert
+        assert_eq!(Duration::ZERO, actual);
+    }
+
+    #[test]
+    fn rate_limited_provider_passes_quotes_through() {
+        // assign
+        let fake = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(1, 1.0, 2.0)],
+        });
+        let config = RateLimitConfig {
+            per_second: Some(100.0),
+            per_minute: None,
+        };
+        let provider = RateLimitedProvider::new(fake, config);
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1000).unwrap();
+
+        // act
+        let actual =
+            block_on(provider.get_quote_history("AAPL", start, end, Interval::OneDay)).unwrap();
+
+        // assert
+        assert_eq!(1, actual.quotes.len());
+    }
+
+    #[test]
+    fn read_config_rate_limit() {
+        // assign
+        let dir = make_test_dir("config");
+        let file_name = dir.join("ticker.json");
+        fs::write(&file_name, r#"{"rate_limit": {"per_minute": 120}}"#).unwrap();
+
+        // act
+        let actual = read_config(&file_name);
+
+        // assert
+        assert_eq!(None, actual.rate_limit.per_second);
+        assert_eq!(Some(120.0), actual.rate_limit.per_minute);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "rate limits need to be more than 0")]
+    fn validate_rate_limit_zero() {
+        // assign
+        let rate_limit = RateLimitConfig {
+            per_second: Some(0.0),
+            per_minute: None,
+        };
+
+        // act
+        validate_rate_limit(rate_limit);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn parse_date_good() {
+        // assign
+        let text = "2024-03-31";
+
+        // act
+        let actual = parse_date(text);
+
+        // assert
+        assert_eq!(
+            Ok(Date::from_calendar_date(2024, Month::March, 31).unwrap()),
+            actual
+        );
+    }
+
+    #[test]
+    fn parse_date_bad_day() {
+        // assign
+        let text = "2024-02-30";
+
+        // act
+        let actual = parse_date(text);
+
+        // assert
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn parse_window_units() {
+        // assign
+        let texts = ["90d", "2w", "3mo", "1y"];
+
+        // act
+        let actual: Vec<i64> = texts
+            .iter()
+            .map(|t| parse_window(t).unwrap().whole_days())
+            .collect();
+
+        // assert
+        assert_eq!(vec![90, 14, 90, 365], actual);
+    }
+
+    #[test]
+    fn parse_window_too_long() {
+        // assign
+        let texts = [
+            "99999999999999999y",
+            "100000y",
+            "99999999999999y",
+            "3650001d",
+        ];
+
+        // act
+        let actual: Vec<bool> = texts.iter().map(|t| parse_window(t).is_err()).collect();
+        let longest = parse_window("10000y").unwrap();
+
+        // assert
+        assert_eq!([true, true, true, true], actual.as_slice());
+        assert_eq!(3_650_000, longest.whole_days());
+    }
+
+    #[test]
+    fn parse_window_no_unit() {
+        // assign
+        let text = "90";
+
+        // act
+        let actual = parse_window(text);
+
+        // assert
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn date_range_default_is_30_days_ending_yesterday() {
+        // assign
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+
+        // act
+        let actual = DateRange::resolve(None, None, None, Some(2), now);
+
+        // assert
+        assert_eq!(now - time::Duration::days(3), actual.end);
+        assert_eq!(30, (actual.end - actual.start).whole_days());
+    }
+
+    #[test]
+    fn date_range_start_and_end_cover_whole_days() {
+        // assign
+        let start = Date::from_calendar_date(2024, Month::January, 1).unwrap();
+        let end = Date::from_calendar_date(2024, Month::March, 31).unwrap();
+
+        // act
+        let actual = DateRange::resolve(
+            Some(start),
+            Some(end),
+            None,
+            None,
+            OffsetDateTime::now_utc(),
+        );
+
+        // assert
+        assert_eq!(1_704_067_200, actual.start.unix_timestamp());
+        assert_eq!(1_711_929_599, actual.end.unix_timestamp());
+    }
+
+    #[test]
+    fn date_range_window_before_end() {
+        // assign
+        let end = Date::from_calendar_date(2024, Month::March, 31).unwrap();
+        let window = parse_window("90d").unwrap();
+
+        // act
+        let actual = DateRange::resolve(
+            None,
+            Some(end),
+            Some(window),
+            None,
+            OffsetDateTime::now_utc(),
+        );
+
+        // assert
+        assert_eq!(actual.end - window, actual.start);
+    }
+
+    #[test]
+    #[should_panic(expected = "start needs to be on or before end")]
+    fn validate_args_start_after_end() {
+        // assign
+        let file_name = PathBuf::from("testdata.txt");
+        let dir = PathBuf::from(".");
+        let log = PathBuf::from("testdata.txt");
+        let start = Date::from_calendar_date(2024, Month::March, 31).ok();
+        let end = Date::from_calendar_date(2024, Month::January, 1).ok();
+
+        // act
+        validate_args(&file_name, &dir, &log, None, start, end);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "start needs to be on or before end")]
+    fn validate_range_start_after_default_end() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let range = DateRange::resolve(Some(now.date()), None, None, None, now);
+
+        // act
+        validate_range(&range);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "start needs to be on or before end")]
+    fn validate_range_start_after_days_ago_end() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let start = (now - time::Duration::days(5)).date();
+        let range = DateRange::resolve(Some(start), None, None, Some(10), now);
+
+        // act
+        validate_range(&range);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn validate_range_start_before_default_end() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let start = (now - time::Duration::days(1)).date();
+        let range = DateRange::resolve(Some(start), None, None, None, now);
+
+        // act
+        validate_range(&range);
+
+        // assert
+        assert!(range.start <= range.end);
+    }
+
+    #[test]
+    #[should_panic(expected = "dates cannot be in the future")]
+    fn validate_args_end_in_future() {
+        // assign
+        let file_name = PathBuf::from("testdata.txt");
+        let dir = PathBuf::from(".");
+        let log = PathBuf::from("testdata.txt");
+        let end = OffsetDateTime::now_utc().date().next_day();
+
+        // act
+        validate_args(&file_name, &dir, &log, None, None, end);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn interval_round_trips() {
+        // assign
+        let texts = vec!["1m", "5m", "15m", "1h", "1d", "1wk", "1mo"];
+
+        // act
+        let actual: Vec<String> = texts
+            .iter()
+            .map(|t| t.parse::<Interval>().unwrap().to_string())
+            .collect();
+
+        // assert
+        assert_eq!(texts, actual);
+    }
+
+    #[test]
+    fn interval_unknown() {
+        // assign
+        let text = "2d";
+
+        // act
+        let actual = text.parse::<Interval>();
+
+        // assert
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn validate_lookback_daily_has_no_limit() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let range = DateRange::resolve(None, None, parse_window("10y").ok(), None, now);
+
+        // act
+        validate_lookback(ProviderKind::Yahoo, Interval::OneDay, &range, now);
+
+        // assert
+        assert!(true);
+    }
+
+    #[test]
+    #[should_panic(expected = "the 1m interval only goes back 7 days")]
+    fn validate_lookback_minutes_too_far_back() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let range = DateRange::resolve(None, None, None, None, now);
+
+        // act
+        validate_lookback(ProviderKind::Yahoo, Interval::OneMinute, &range, now);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn validate_lookback_file_provider_has_no_limit() {
+        // assign
+        let now = OffsetDateTime::now_utc();
+        let range = DateRange::resolve(None, None, None, None, now);
+
+        // act
+        validate_lookback(ProviderKind::File, Interval::OneMinute, &range, now);
+
+        // assert
+        assert!(true);
+    }
+
+    #[test]
+    fn process_symbols_warmup_starts_no_earlier_than_provider_keeps() {
+        // assign
+        let dir = make_test_dir("warmup_earliest");
+        let fixture_dir = make_test_dir("warmup_earliest_fixtures");
+        let provider = Arc::new(RecordingProvider::new(
+            Arc::new(FakeProvider {
+                quotes: vec![fake_quote(864000, 1.0, 2.0)],
+            }),
+            fixture_dir.clone(),
+        ));
+        let earliest = OffsetDateTime::from_unix_timestamp(604800).unwrap();
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 11).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 12).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            indicators: vec![Indicator::Sma(5)],
+            earliest: Some(earliest),
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        let fixture: Fixture =
+            serde_json::from_str(&fs::read_to_string(fixture_dir.join("AAPL.json")).unwrap())
+                .unwrap();
+        assert_eq!(604800, fixture.start);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn format_timestamp_daily_uses_exchange_date() {
+        // assign
+        // 2024-03-01 02:00 utc is still 2024-02-29 in new york (utc-5)
+        let timestamp = 1_709_258_400;
+
+        // act
+        let actual = format_timestamp(timestamp, -18_000, Interval::OneDay);
+
+        // assert
+        assert_eq!("2024-02-29", actual);
+    }
+
+    #[test]
+    fn format_timestamp_intraday_has_time_and_offset() {
+        // assign
+        let timestamp = 1_709_303_400;
+
+        // act
+        let actual = format_timestamp(timestamp, -18_000, Interval::FiveMinutes);
+
+        // assert
+        assert_eq!("2024-03-01T09:30:00-05:00", actual);
+    }
+
+    #[test]
+    fn output_mode_parses() {
+        // assign
+        let texts = ["gains", "OHLCV", "ohlcv+gains"];
+
+        // act
+        let actual: Vec<OutputMode> = texts.iter().map(|t| t.parse().unwrap()).collect();
+
+        // assert
+        assert_eq!(
+            vec![OutputMode::Gains, OutputMode::Ohlcv, OutputMode::OhlcvGains],
+            actual
+        );
+    }
+
+    #[test]
+    fn process_symbols_ohlcv_output() {
+        // assign
+        let dir = make_test_dir("ohlcv_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![Quote {
+                timestamp: 0,
+                open: 1.0,
+                high: 2.5,
+                low: 0.5,
+                volume: 100,
+                close: 2.0,
+                adjclose: 1.9,
+            }],
+        });
+
+        // act
+        process_symbols(
+            vec!["AAPL"],
+            &dir,
+            None,
+            test_settings(OutputMode::Ohlcv),
+            provider,
+        );
+
+        // assert
+        assert_eq!(
+            "date,open,high,low,close,adjclose,volume\n1970-01-01,1.0,2.5,0.5,2.0,1.9,100\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_ohlcv_and_gains_output() {
+        // assign
+        let dir = make_test_dir("ohlcv_gains_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0)],
+        });
+
+        // act
+        process_symbols(
+            vec!["AAPL"],
+            &dir,
+            None,
+            test_settings(OutputMode::OhlcvGains),
+            provider,
+        );
+
+        // assert
+        assert_eq!(
+            "date,open,high,low,close,adjclose,volume,gain\n1970-01-01,1.0,0.0,0.0,2.0,0.0,0,1.0\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn metric_parses() {
+        // assign
+        let texts = [
+            "open-close",
+            "close-close",
+            "ADJCLOSE",
+            "log",
+            "gap",
+            "range",
+        ];
+
+        // act
+        let actual: Vec<Metric> = texts.iter().map(|t| t.parse().unwrap()).collect();
+
+        // assert
+        assert_eq!(
+            vec![
+                Metric::OpenClose,
+                Metric::CloseClose,
+                Metric::AdjClose,
+                Metric::Log,
+                Metric::Gap,
+                Metric::Range
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn metric_apply_compares_with_prior_quote() {
+        // assign
+        let prior = Quote {
+            adjclose: 4.0,
+            ..fake_quote(0, 1.0, 2.0)
+        };
+        let quote = Quote {
+            high: 5.0,
+            low: 2.0,
+            adjclose: 5.0,
+            ..fake_quote(86400, 3.0, 4.0)
+        };
+
+        // act
+        let close_close = Metric::CloseClose.apply(Some(&prior), &quote, Adjustment::default());
+        let adjclose = Metric::AdjClose.apply(Some(&prior), &quote, Adjustment::default());
+        let log = Metric::Log.apply(Some(&prior), &quote, Adjustment::default());
+        let gap = Metric::Gap.apply(Some(&prior), &quote, Adjustment::default());
+        let range = Metric::Range.apply(Some(&prior), &quote, Adjustment::default());
+
+        // assert
+        assert_eq!(Some(1.0), close_close);
+        assert_eq!(Some(0.25), adjclose);
+        assert_eq!(Some(2.0_f64.ln()), log);
+        assert_eq!(Some(0.5), gap);
+        assert_eq!(Some(1.0), range);
+    }
+
+    #[test]
+    fn metric_apply_without_prior_quote() {
+        // assign
+        let quote = fake_quote(0, 1.0, 2.0);
+
+        // act
+        let open_close = Metric::OpenClose.apply(None, &quote, Adjustment::default());
+        let close_close = Metric::CloseClose.apply(None, &quote, Adjustment::default());
+
+        // assert
+        assert_eq!(Some(1.0), open_close);
+        assert_eq!(None, close_close);
+    }
+
+    #[test]
+    fn process_symbols_look_back_metric_fetches_prior_quote() {
+        // assign
+        let dir = make_test_dir("look_back_prior");
+        let fixture_dir = make_test_dir("look_back_prior_fixtures");
+        let provider = Arc::new(RecordingProvider::new(
+            Arc::new(FakeProvider {
+                quotes: vec![
+                    fake_quote(0, 1.0, 2.0),
+                    fake_quote(86400, 2.0, 3.0),
+                    fake_quote(172800, 3.0, 6.0),
+                ],
+            }),
+            fixture_dir.clone(),
+        ));
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 2).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 3).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::CloseClose,
+            summary: true,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-02,0.5\n1970-01-03,1.0\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        let fixture: Fixture =
+            serde_json::from_str(&fs::read_to_string(fixture_dir.join("AAPL.json")).unwrap())
+                .unwrap();
+        assert!(fixture.start < 86400);
+        let summary = fs::read_to_string(dir.join("summary.csv")).unwrap();
+        assert!(summary.lines().nth(1).unwrap().starts_with("AAPL,2,0.75,"));
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn metric_range_without_rounding_through_open() {
+        // assign
+        let quote = Quote {
+            high: 100.3,
+            low: 100.1,
+            ..fake_quote(0, 100.0, 100.2)
+        };
+
+        // act
+        let range = Metric::Range.apply(None, &quote, Adjustment::default());
+
+        // assert
+        assert_eq!(Some((100.3 - 100.1) / 100.0), range);
+    }
+
+    #[test]
+    fn metric_apply_zero_guard() {
+        // assign
+        let prior = fake_quote(0, 0.0, 0.0);
+        let quote = fake_quote(86400, 0.0, 2.0);
+
+        // act
+        let close_close = Metric::CloseClose.apply(Some(&prior), &quote, Adjustment::default());
+        let log = Metric::Log.apply(Some(&prior), &quote, Adjustment::default());
+        let range = Metric::Range.apply(Some(&prior), &quote, Adjustment::default());
+
+        // assert
+        assert_eq!(Some(0.0), close_close);
+        assert_eq!(Some(0.0), log);
+        assert_eq!(Some(0.0), range);
+    }
+
+    #[test]
+    fn process_symbols_close_close_metric() {
+        // assign
+        let dir = make_test_dir("close_close_metric");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 3.0)],
+        });
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 1).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 2).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::CloseClose,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-02,0.5\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// a 2:1 split on the second day and a 0.5 dividend on the third, the closes split-adjusted
+    fn history_with_events() -> QuoteHistory {
+        QuoteHistory {
+            splits: vec![SplitEvent {
+                timestamp: 86400,
+                numerator: 2.0,
+                denominator: 1.0,
+            }],
+            dividends: vec![DividendEvent {
+                timestamp: 172800,
+                amount: 0.5,
+            }],
+            ..QuoteHistory::new(vec![
+                fake_quote(0, 5.0, 5.0),
+                fake_quote(86400, 5.0, 5.0),
+                fake_quote(172800, 5.0, 5.0),
+            ])
+        }
+    }
+
+    #[test]
+    fn adjustment_between_picks_events_in_window() {
+        // assign
+        let history = history_with_events();
+
+        // act
+        let split_day = Adjustment::between(&history, 0, 86400);
+        let dividend_day = Adjustment::between(&history, 86400, 172800);
+        let both = Adjustment::between(&history, 0, 172800);
+        let neither = Adjustment::between(&history, 172800, 259200);
+
+        // assert
+        assert_eq!(Adjustment::default(), split_day);
+        assert_eq!(Adjustment { dividend: 0.5 }, dividend_day);
+        assert_eq!(Adjustment { dividend: 0.5 }, both);
+        assert_eq!(Adjustment::default(), neither);
+    }
+
+    #[test]
+    fn metric_total_adds_back_dividend() {
+        // assign
+        let prior = fake_quote(0, 10.0, 10.0);
+        let quote = fake_quote(86400, 10.0, 10.0);
+        let adjustment = Adjustment { dividend: 1.0 };
+
+        // act
+        let total = Metric::Total.apply(Some(&prior), &quote, adjustment);
+        let close_close = Metric::CloseClose.apply(Some(&prior), &quote, adjustment);
+
+        // assert
+        assert_eq!(Some(0.1), total);
+        assert_eq!(Some(0.0), close_close);
+    }
+
+    #[test]
+    fn event_rows_in_date_order() {
+        // assign
+        let history = history_with_events();
+
+        // act
+        let actual = event_rows(&history, Interval::OneDay, 0);
+
+        // assert
+        assert_eq!(
+            vec![
+                vec![
+                    Cell::Text(String::from("1970-01-02")),
+                    Cell::Text(String::from("split")),
+                    Cell::Text(String::from("2:1"))
+                ],
+                vec![
+                    Cell::Text(String::from("1970-01-03")),
+                    Cell::Text(String::from("dividend")),
+                    Cell::Number(0.5)
+                ],
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn process_symbols_total_metric_writes_events() {
+        // assign
+        let dir = make_test_dir("total_metric");
+        let fixture_dir = make_test_dir("total_metric_fixtures");
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 1).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 3).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::Total,
+            ..test_settings(OutputMode::Gains)
+        };
+        let fixture = Fixture {
+            symbol: String::from("AAPL"),
+            start: settings.range.start.unix_timestamp(),
+            end: settings.range.end.unix_timestamp(),
+            interval: Interval::OneDay,
+            response: Ok(history_with_events()),
+        };
+        fs::write(
+            fixture_dir.join("AAPL.json"),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+        let provider = Arc::new(ReplayProvider::new(fixture_dir.clone()));
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-02,0.0\n1970-01-03,0.1\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        assert_eq!(
+            "date,event,value\n1970-01-02,split,2:1\n1970-01-03,dividend,0.5\n",
+            fs::read_to_string(dir.join("AAPL.events")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_run_wide_format_writes_no_events_file() {
+        // assign
+        let dir = make_test_dir("run_wide_events");
+        let fixture_dir = make_test_dir("run_wide_events_fixtures");
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 1).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 3).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            format: OutputFormat::Matrix,
+            ..test_settings(OutputMode::Gains)
+        };
+        let fixture = Fixture {
+            symbol: String::from("AAPL"),
+            start: settings.range.start.unix_timestamp(),
+            end: settings.range.end.unix_timestamp(),
+            interval: Interval::OneDay,
+            response: Ok(history_with_events()),
+        };
+        fs::write(
+            fixture_dir.join("AAPL.json"),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+        let provider = Arc::new(ReplayProvider::new(fixture_dir.clone()));
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert!(dir.join("matrix.csv").exists());
+        assert!(!dir.join("AAPL.events").exists());
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_events_leave_out_warmup() {
+        // assign
+        let dir = make_test_dir("events_warmup");
+        let fixture_dir = make_test_dir("events_warmup_fixtures");
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(Date::from_calendar_date(1970, Month::January, 2).unwrap()),
+                Some(Date::from_calendar_date(1970, Month::January, 3).unwrap()),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::CloseClose,
+            ..test_settings(OutputMode::Gains)
+        };
+        let history = QuoteHistory {
+            dividends: vec![
+                DividendEvent {
+                    timestamp: 0,
+                    amount: 0.25,
+                },
+                DividendEvent {
+                    timestamp: 172800,
+                    amount: 0.5,
+                },
+            ],
+            ..QuoteHistory::new(vec![
+                fake_quote(0, 5.0, 5.0),
+                fake_quote(86400, 5.0, 5.0),
+                fake_quote(172800, 5.0, 5.0),
+            ])
+        };
+        let fixture = Fixture {
+            symbol: String::from("AAPL"),
+            start: settings.range.start.unix_timestamp(),
+            end: settings.range.end.unix_timestamp(),
+            interval: Interval::OneDay,
+            response: Ok(history),
+        };
+        fs::write(
+            fixture_dir.join("AAPL.json"),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+        let provider = Arc::new(ReplayProvider::new(fixture_dir.clone()));
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,event,value\n1970-01-03,dividend,0.5\n",
+            fs::read_to_string(dir.join("AAPL.events")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_total_metric_over_nvda_split() {
+        // assign
+        let dir = make_test_dir("total_metric_nvda");
+        let fixture_dir = make_test_dir("total_metric_nvda_fixtures");
+        let start = Date::from_calendar_date(2024, Month::June, 7).unwrap();
+        let end = Date::from_calendar_date(2024, Month::June, 12).unwrap();
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(start),
+                Some(end),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::Total,
+            ..test_settings(OutputMode::Gains)
+        };
+        // yahoo's daily closes, from the one before the range, around the 10:1 split of 2024-06-10, already split-adjusted, and
+        // the 0.01 dividend that went ex on 2024-06-11
+        let history = QuoteHistory {
+            splits: vec![SplitEvent {
+                timestamp: 1718026200,
+                numerator: 10.0,
+                denominator: 1.0,
+            }],
+            dividends: vec![DividendEvent {
+                timestamp: 1718112600,
+                amount: 0.01,
+            }],
+            ..QuoteHistory::new(vec![
+                fake_quote(1717680600, 120.998, 120.998),
+                fake_quote(1717767000, 120.888, 120.888),
+                fake_quote(1718026200, 121.79, 121.79),
+                fake_quote(1718112600, 120.91, 120.91),
+                fake_quote(1718199000, 125.2, 125.2),
+            ])
+        };
+        let fixture = Fixture {
+            symbol: String::from("NVDA"),
+            start: settings.range.start.unix_timestamp(),
+            end: settings.range.end.unix_timestamp(),
+            interval: Interval::OneDay,
+            response: Ok(history),
+        };
+        fs::write(
+            fixture_dir.join("NVDA.json"),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+        let provider = Arc::new(ReplayProvider::new(fixture_dir.clone()));
+
+        // act
+        process_symbols(vec!["NVDA"], &dir, None, settings, provider);
+
+        // assert
+        let gains: Vec<f64> = fs::read_to_string(dir.join("NVDA"))
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(1).unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(4, gains.len());
+        assert!((gains[0] - (120.888 / 120.998 - 1.0)).abs() < 1e-9);
+        assert!((gains[1] - (121.79 / 120.888 - 1.0)).abs() < 1e-9);
+        assert!((gains[2] - (120.92 / 121.79 - 1.0)).abs() < 1e-9);
+        assert!((gains[3] - (125.2 / 120.91 - 1.0)).abs() < 1e-9);
+        assert!(gains.iter().all(|gain| gain.abs() < 0.05));
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_without_events_writes_no_events_file() {
+        // assign
+        let dir = make_test_dir("no_events");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0)],
+        });
+
+        // act
+        process_symbols(
+            vec!["AAPL"],
+            &dir,
+            None,
+            test_settings(OutputMode::Gains),
+            provider,
+        );
+
+        // assert
+        assert!(!dir.join("AAPL.events").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn output_format_parses() {
+        // assign
+        let texts = [
+            "csv", "JSON", "jsonl", "parquet", "arrow", "sqlite", "postgres", "matrix",
+        ];
+
+        // act
+        let actual: Vec<OutputFormat> = texts.iter().map(|t| t.parse().unwrap()).collect();
+
+        // assert
+        assert_eq!(
+            vec![
+                OutputFormat::Csv,
+                OutputFormat::Json,
+                OutputFormat::Jsonl,
+                OutputFormat::Parquet,
+                OutputFormat::Arrow,
+                OutputFormat::Sqlite,
+                OutputFormat::Postgres,
+                OutputFormat::Matrix
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn json_document_holds_run_and_rows() {
+        // assign
+        let start = Date::from_calendar_date(2024, Month::March, 1).unwrap();
+        let end = Date::from_calendar_date(2024, Month::March, 4).unwrap();
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(start),
+                Some(end),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            ..test_settings(OutputMode::Gains)
+        };
+        let rows = [OutputRow {
+            date: String::from("2024-03-01"),
+            quote: fake_quote(0, 1.0, 2.0),
+            gain: 1.0,
+            rolling: Vec::new(),
+            indicators: Vec::new(),
+        }];
+
+        // act
+        let actual = json_document("AAPL", &rows, &settings);
+
+        // assert
+        assert_eq!(
+            serde_json::json!({
+                "symbol": "AAPL",
+                "start": "2024-03-01",
+                "end": "2024-03-04",
+                "interval": "1d",
+                "metric": "open-close",
+                "rows": [{ "date": "2024-03-01", "gain": 1.0 }],
+            }),
+            actual
+        );
+    }
+
+    #[test]
+    fn process_symbols_json_output() {
+        // assign
+        let dir = make_test_dir("json_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Json,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        let contents = fs::read_to_string(dir.join("AAPL.json")).unwrap();
+        let document: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!("AAPL", document["symbol"]);
+        assert_eq!(
+            serde_json::json!([{ "date": "1970-01-01", "gain": 1.0 }]),
+            document["rows"]
+        );
+        assert!(!dir.join("AAPL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_jsonl_output() {
+        // assign
+        let dir = make_test_dir("jsonl_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Jsonl,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "{\"symbol\":\"AAPL\",\"date\":\"1970-01-01\",\"gain\":1.0}\n{\"symbol\":\"AAPL\",\"date\":\"1970-01-02\",\"gain\":-0.5}\n",
+            fs::read_to_string(dir.join("AAPL.jsonl")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn record_batch_has_a_row_per_quote() {
+        // assign
+        let rows = [
+            (
+                String::from("AAPL"),
+                OutputRow {
+                    date: String::from("1970-01-01"),
+                    quote: fake_quote(0, 1.0, 2.0),
+                    gain: 1.0,
+                    rolling: Vec::new(),
+                    indicators: Vec::new(),
+                },
+            ),
+            (
+                String::from("MSFT"),
+                OutputRow {
+                    date: String::from("1970-01-01"),
+                    quote: fake_quote(0, 2.0, 1.0),
+                    gain: -0.5,
+                    rolling: Vec::new(),
+                    indicators: Vec::new(),
+                },
+            ),
+        ];
+
+        // act
+        let batch = record_batch(&rows).unwrap();
+
+        // assert
+        let columns: Vec<String> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        assert_eq!(
+            vec!["symbol", "date", "open", "high", "low", "close", "adjclose", "volume", "gain"],
+            columns
+        );
+        assert_eq!(2, batch.num_rows());
+    }
+
+    #[test]
+    fn process_symbols_parquet_output() {
+        // assign
+        let dir = make_test_dir("parquet_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Parquet,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["MSFT", "AAPL"], &dir, Some(2), settings, provider);
+
+        // assert
+        let file = fs::File::open(dir.join("ticker.parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        let symbols = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(
+            vec!["AAPL", "AAPL", "MSFT", "MSFT"],
+            symbols.iter().map(|s| s.unwrap()).collect::<Vec<&str>>()
+        );
+        assert!(!dir.join("AAPL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_arrow_output() {
+        // assign
+        let dir = make_test_dir("arrow_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Arrow,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL", "FAIL"], &dir, None, settings, provider);
+
+        // assert
+        let file = fs::File::open(dir.join("ticker.arrow")).unwrap();
+        let reader = FileReader::try_new(file, None).unwrap();
+        let rows: usize = reader.map(|b| b.unwrap().num_rows()).sum();
+        assert_eq!(1, rows);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_sqlite_upserts_bars() {
+        // assign
+        let dir = make_test_dir("sqlite_upsert");
+        let file_name = dir.join("ticker.sqlite");
+        let settings = test_settings(OutputMode::Gains);
+        let row = |timestamp, close, gain| OutputRow {
+            date: String::from("1970-01-01"),
+            quote: fake_quote(timestamp, 1.0, close),
+            gain,
+            rolling: Vec::new(),
+            indicators: Vec::new(),
+        };
+        let first = [
+            (String::from("AAPL"), row(0, 2.0, 1.0)),
+            (String::from("AAPL"), row(86400, 3.0, 2.0)),
+        ];
+        let second = [
+            (String::from("AAPL"), row(86400, 4.0, 3.0)),
+            (String::from("AAPL"), row(172800, 5.0, 4.0)),
+        ];
+
+        // act
+        let first_run = write_sqlite(&file_name, &first, &settings).unwrap();
+        let second_run = write_sqlite(&file_name, &second, &settings).unwrap();
+
+        // assert
+        let connection = rusqlite::Connection::open(&file_name).unwrap();
+        let mut statement = connection
+            .prepare("SELECT timestamp, close, run_id FROM bars ORDER BY timestamp")
+            .unwrap();
+        let bars: Vec<(i64, f64, i64)> = statement
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                (0, 2.0, first_run),
+                (86400, 4.0, second_run),
+                (172800, 5.0, second_run)
+            ],
+            bars
+        );
+        let symbol: (i64, i64) = connection
+            .query_row(
+                "SELECT first_run, last_run FROM symbols WHERE symbol = 'AAPL'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((first_run, second_run), symbol);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_sqlite_output() {
+        // assign
+        let dir = make_test_dir("sqlite_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Sqlite,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL", "MSFT"], &dir, Some(2), settings, provider);
+
+        // assert
+        let connection = rusqlite::Connection::open(dir.join("ticker.sqlite")).unwrap();
+        let bars: i64 = connection
+            .query_row("SELECT COUNT(*) FROM bars", [], |r| r.get(0))
+            .unwrap();
+        let runs: (i64, i64) = connection
+            .query_row("SELECT symbols, bars FROM runs", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(4, bars);
+        assert_eq!((2, 4), runs);
+        assert!(!dir.join("AAPL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validate_postgres_other_format_needs_nothing() {
+        // assign
+        let postgres = PostgresConfig::default();
+
+        // act
+        validate_postgres(OutputFormat::Csv, &postgres);
+
+        // assert
+        assert!(true);
+    }
+
+    #[test]
+    #[cfg(feature = "postgres")]
+    #[should_panic(expected = "postgres_url is needed for the postgres format")]
+    fn validate_postgres_needs_url() {
+        // assign
+        let postgres = PostgresConfig::default();
+
+        // act
+        validate_postgres(OutputFormat::Postgres, &postgres);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[cfg(not(feature = "postgres"))]
+    #[should_panic(expected = "the postgres format needs ticker built with --features postgres")]
+    fn validate_postgres_needs_feature() {
+        // assign
+        let postgres = PostgresConfig {
+            url: Some(String::from("host=localhost")),
+            ..Default::default()
+        };
+
+        // act
+        validate_postgres(OutputFormat::Postgres, &postgres);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[cfg(feature = "postgres")]
+    fn postgres_table_name_is_quoted() {
+        // assign
+        let postgres = PostgresConfig {
+            schema: Some(String::from("market")),
+            table: Some(String::from("daily \"bars\"")),
+            ..Default::default()
+        };
+
+        // act
+        let actual = table_name(&postgres);
+        let defaults = table_name(&PostgresConfig::default());
+
+        // assert
+        assert_eq!("\"market\".\"daily \"\"bars\"\"\"", actual);
+        assert_eq!("\"public\".\"bars\"", defaults);
+    }
+
+    #[test]
+    #[cfg(feature = "postgres")]
+    fn postgres_upsert_updates_on_conflict() {
+        // assign
+        let postgres = PostgresConfig::default();
+
+        // act
+        let actual = upsert_sql(&postgres);
+
+        // assert
+        assert!(actual.starts_with("INSERT INTO \"public\".\"bars\" (symbol, interval, timestamp,"));
+        assert!(actual.contains(
+            "SELECT DISTINCT ON (symbol, interval, timestamp) symbol, interval, timestamp,"
+        ));
+        assert!(actual.contains(
+            "ON CONFLICT (symbol, interval, timestamp) DO UPDATE SET date = excluded.date,"
+        ));
+        assert!(actual.ends_with("metric = excluded.metric"));
+    }
+
+    /// runs against the postgres in TICKER_TEST_POSTGRES_URL, like a local container started with
+    /// `docker run -e POSTGRES_PASSWORD=ticker -p 5432:5432 postgres`, and does nothing without it
+    #[test]
+    #[cfg(feature = "postgres")]
+    fn write_postgres_upserts_bars() {
+        // assign
+        let url = match std::env::var("TICKER_TEST_POSTGRES_URL") {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        let postgres = PostgresConfig {
+            url: Some(url.clone()),
+            schema: Some(String::from("ticker_test")),
+            table: Some(format!("bars_{}", fastrand::u32(..))),
+        };
+        let settings = RunSettings {
+            postgres: postgres.clone(),
+            ..test_settings(OutputMode::Gains)
+        };
+        let row = |timestamp, close, gain| OutputRow {
+            date: String::from("1970-01-01"),
+            quote: fake_quote(timestamp, 1.0, close),
+            gain,
+            rolling: Vec::new(),
+            indicators: Vec::new(),
+        };
+        let first = [
+            (String::from("AAPL"), row(0, 2.0, 1.0)),
+            (String::from("AAPL"), row(86400, 3.0, 2.0)),
+        ];
+        let second = [
+            (String::from("AAPL"), row(86400, 4.0, 3.0)),
+            (String::from("AAPL"), row(86400, 4.0, 3.0)),
+        ];
+
+        // act
+        block_on(write_postgres(&postgres, &first, &settings)).unwrap();
+        block_on(write_postgres(&postgres, &second, &settings)).unwrap();
+
+        // assert
+        let closes: Vec<f64> = block_on(async {
+            let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls)
+                .await
+                .unwrap();
+            tokio::spawn(connection);
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT close FROM {} ORDER BY timestamp",
+                        table_name(&postgres)
+                    ),
+                    &[],
+                )
+                .await
+                .unwrap();
+            client
+                .batch_execute(&format!("DROP TABLE {}", table_name(&postgres)))
+                .await
+                .unwrap();
+            rows.iter().map(|r| r.get(0)).collect()
+        });
+        assert_eq!(vec![2.0, 4.0], closes);
+    }
+
+    /// AAPL on the first and third day, MSFT on the second and third
+    fn gappy_run_rows() -> Vec<(String, OutputRow)> {
+        let row = |date: &str, gain| OutputRow {
+            date: String::from(date),
+            quote: fake_quote(0, 1.0, 1.0),
+            gain,
+            rolling: Vec::new(),
+            indicators: Vec::new(),
+        };
+        vec![
+            (String::from("MSFT"), row("2024-03-04", 0.5)),
+            (String::from("MSFT"), row("2024-03-05", 0.25)),
+            (String::from("AAPL"), row("2024-03-01", 1.0)),
+            (String::from("AAPL"), row("2024-03-05", 2.0)),
+        ]
+    }
+
+    /// the matrix with every cell as it would be written
+    fn matrix_text(matrix: Vec<Vec<Cell>>) -> Vec<String> {
+        matrix
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| match cell {
+                        Cell::Text(text) => text,
+                        Cell::Number(number) => number.to_string(),
+                        Cell::Count(count) => count.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_policy_parses() {
+        // assign
+        let texts = ["empty", "NaN", "ffill"];
+
+        // act
+        let actual: Vec<FillPolicy> = texts.iter().map(|t| t.parse().unwrap()).collect();
+
+        // assert
+        assert_eq!(
+            vec![FillPolicy::Empty, FillPolicy::NaN, FillPolicy::ForwardFill],
+            actual
+        );
+    }
+
+    #[test]
+    fn build_matrix_empty_fill() {
+        // assign
+        let rows = gappy_run_rows();
+
+        // act
+        let actual = matrix_text(build_matrix(&rows, FillPolicy::Empty));
+
+        // assert
+        assert_eq!(
+            vec![
+                "date,AAPL,MSFT",
+                "2024-03-01,1,",
+                "2024-03-04,,0.5",
+                "2024-03-05,2,0.25"
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn build_matrix_nan_fill() {
+        // assign
+        let rows = gappy_run_rows();
+
+        // act
+        let actual = matrix_text(build_matrix(&rows, FillPolicy::NaN));
+
+        // assert
+        assert_eq!(
+            vec![
+                "date,AAPL,MSFT",
+                "2024-03-01,1,NaN",
+                "2024-03-04,NaN,0.5",
+                "2024-03-05,2,0.25"
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn build_matrix_forward_fill() {
+        // assign
+        let rows = gappy_run_rows();
+
+        // act
+        let actual = matrix_text(build_matrix(&rows, FillPolicy::ForwardFill));
+
+        // assert
+        assert_eq!(
+            vec![
+                "date,AAPL,MSFT",
+                "2024-03-01,1,",
+                "2024-03-04,1,0.5",
+                "2024-03-05,2,0.25"
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn process_symbols_matrix_output() {
+        // assign
+        let dir = make_test_dir("matrix_output");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            format: OutputFormat::Matrix,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["MSFT", "AAPL"], &dir, Some(2), settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,AAPL,MSFT\n1970-01-01,1.0,1.0\n1970-01-02,-0.5,-0.5\n",
+            fs::read_to_string(dir.join("matrix.csv")).unwrap()
+        );
+        assert!(!dir.join("AAPL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn last_stored_date_csv() {
+        // assign
+        let dir = make_test_dir("last_stored_csv");
+        fs::write(
+            dir.join("AAPL"),
+            "date,gain\n1970-01-01,1.0\n1970-01-02,0.5\n",
+        )
+        .unwrap();
+        fs::write(dir.join("MSFT"), "date,gain\n").unwrap();
+        fs::write(dir.join("GOOG"), "date,open,gain\n1970-01-01,1.0,0.5\n").unwrap();
+
+        // act
+        let settings = test_settings(OutputMode::Gains);
+        let aapl = last_stored_date(&dir, "AAPL", &settings);
+        let msft = last_stored_date(&dir, "MSFT", &settings);
+        let goog = last_stored_date(&dir, "GOOG", &settings);
+        let missing = last_stored_date(&dir, "IBM", &settings);
+
+        // assert
+        assert_eq!(Some(String::from("1970-01-02")), aapl);
+        assert_eq!(None, msft);
+        assert_eq!(None, goog);
+        assert_eq!(None, missing);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn last_stored_date_jsonl() {
+        // assign
+        let dir = make_test_dir("last_stored_jsonl");
+        fs::write(
+            dir.join("AAPL.jsonl"),
+            "{\"symbol\":\"AAPL\",\"date\":\"1970-01-01\",\"gain\":1.0}\n{\"symbol\":\"AAPL\",\"date\":\"1970-01-02\",\"gain\":0.5}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("GOOG.jsonl"),
+            "{\"symbol\":\"GOOG\",\"date\":\"1970-01-01\",\"open\":1.0,\"gain\":0.5}\n",
+        )
+        .unwrap();
+
+        // act
+        let settings = RunSettings {
+            format: OutputFormat::Jsonl,
+            ..test_settings(OutputMode::Gains)
+        };
+        let aapl = last_stored_date(&dir, "AAPL", &settings);
+        let goog = last_stored_date(&dir, "GOOG", &settings);
+
+        // assert
+        assert_eq!(Some(String::from("1970-01-02")), aapl);
+        assert_eq!(None, goog);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resume_start_date_and_time() {
+        // assign
+        let date = "2024-03-05";
+        let time = "2024-03-05T15:30:00-05:00";
+
+        // act
+        let from_date = resume_start(date).unwrap();
+        let from_time = resume_start(time).unwrap();
+
+        // assert
+        assert_eq!(
+            Date::from_calendar_date(2024, Month::March, 4).unwrap(),
+            from_date.date()
+        );
+        assert_eq!(0, from_date.hour());
+        assert_eq!(1709670600, from_time.unix_timestamp());
+        assert_eq!(None, resume_start("yesterday"));
+    }
+
+    #[test]
+    #[should_panic(expected = "incremental needs the csv or jsonl format")]
+    fn validate_incremental_parquet() {
+        // assign
+        let format = OutputFormat::Parquet;
+
+        // act
+        validate_incremental(true, format);
+
+        // assert
+        assert!(false);
+    }
+
+    /// settings for an incremental close-close run over the first ten days of 1970
+    fn incremental_settings() -> RunSettings {
+        let start = Date::from_calendar_date(1970, Month::January, 1).unwrap();
+        let end = Date::from_calendar_date(1970, Month::January, 10).unwrap();
+        RunSettings {
+            range: DateRange::resolve(
+                Some(start),
+                Some(end),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            metric: Metric::CloseClose,
+            incremental: true,
+            ..test_settings(OutputMode::Gains)
+        }
+    }
+
+    #[test]
+    fn process_symbols_incremental_appends_missing_days() {
+        // assign
+        let dir = make_test_dir("incremental_output");
+        let data_dir = make_test_dir("incremental_data");
+        let fixture_dir = make_test_dir("incremental_fixtures");
+        fs::write(
+            data_dir.join("AAPL.csv"),
+            "timestamp,open,high,low,volume,close,adjclose\n0,1,1,1,0,1,1\n86400,1,1,1,0,2,2\n172800,1,1,1,0,4,4\n259200,1,1,1,0,5,5\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("AAPL"),
+            "date,gain\n1970-01-01,0.0\n1970-01-02,1.0\n1970-01-03,1.0\n",
+        )
+        .unwrap();
+        let provider = Arc::new(RecordingProvider::new(
+            Arc::new(FileProvider::new(data_dir.clone())),
+            fixture_dir.clone(),
+        ));
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, incremental_settings(), provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-01,0.0\n1970-01-02,1.0\n1970-01-03,1.0\n1970-01-04,0.25\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        let fixture: Fixture =
+            serde_json::from_str(&fs::read_to_string(fixture_dir.join("AAPL.json")).unwrap())
+                .unwrap();
+        assert_eq!(86400, fixture.start);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&data_dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_incremental_up_to_date() {
+        // assign
+        let dir = make_test_dir("incremental_up_to_date");
+        let contents = "date,gain\n1970-01-09,0.0\n1970-01-10,1.0\n";
+        fs::write(dir.join("AAPL"), contents).unwrap();
+        let provider = Arc::new(FlakyProvider {
+            failures: 0,
+            status: "",
+            calls: AtomicU32::new(0),
+        });
+
+        // act
+        process_symbols(
+            vec!["AAPL"],
+            &dir,
+            None,
+            incremental_settings(),
+            provider.clone(),
+        );
+
+        // assert
+        assert_eq!(0, provider.calls.load(Ordering::SeqCst));
+        assert_eq!(contents, fs::read_to_string(dir.join("AAPL")).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_incremental_without_file_writes_all() {
+        // assign
+        let dir = make_test_dir("incremental_new_file");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86400, 2.0, 3.0)],
+        });
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, incremental_settings(), provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-02,0.5\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_incremental_appends_new_events() {
+        // assign
+        let dir = make_test_dir("incremental_events");
+        let fixture_dir = make_test_dir("incremental_events_fixtures");
+        fs::write(dir.join("AAPL"), "date,gain\n1970-01-02,0.0\n").unwrap();
+        fs::write(
+            dir.join("AAPL.events"),
+            "date,event,value\n1970-01-02,split,2:1\n",
+        )
+        .unwrap();
+        let settings = incremental_settings();
+        let fixture = Fixture {
+            symbol: String::from("AAPL"),
+            start: settings.range.start.unix_timestamp(),
+            end: settings.range.end.unix_timestamp(),
+            interval: Interval::OneDay,
+            response: Ok(history_with_events()),
+        };
+        fs::write(
+            fixture_dir.join("AAPL.json"),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+        let provider = Arc::new(ReplayProvider::new(fixture_dir.clone()));
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        assert_eq!(
+            "date,gain\n1970-01-02,0.0\n1970-01-03,0.0\n",
+            fs::read_to_string(dir.join("AAPL")).unwrap()
+        );
+        assert_eq!(
+            "date,event,value\n1970-01-02,split,2:1\n1970-01-03,dividend,0.5\n",
+            fs::read_to_string(dir.join("AAPL.events")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&fixture_dir).unwrap();
+    }
+
+    /// a cache in its own directory that keeps responses for a day
+    fn test_cache(name: &str, max_bytes: u64) -> ResponseCache {
+        ResponseCache {
+            dir: make_test_dir(name),
+            ttl: Duration::from_secs(86400),
+            max_bytes,
+        }
+    }
+
+    /// makes a cached response look like it was written `age` ago
+    fn age_entry(entry: &std::path::Path, age: Duration) {
+        let file = fs::File::options().write(true).open(entry).unwrap();
+        file.set_modified(std::time::SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn cache_entry_named_after_request() {
+        // assign
+        let cache = test_cache("cache_entry", u64::MAX);
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(86400 + 3600).unwrap();
+
+        // act
+        let actual = cache.entry("yahoo", "AAPL", Interval::OneDay, start, end);
+
+        // assert
+        assert_eq!(
+            cache
+                .dir
+                .join("yahoo_AAPL_1d_1970-01-01_1970-01-02.tickercache"),
+            actual
+        );
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_entry_intraday_named_after_timestamps() {
+        // assign
+        let cache = test_cache("cache_entry_intraday", u64::MAX);
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(3600).unwrap();
+        let later = OffsetDateTime::from_unix_timestamp(7200).unwrap();
+
+        // act
+        let actual = cache.entry("yahoo", "AAPL", Interval::OneHour, start, end);
+        let actual_later = cache.entry("yahoo", "AAPL", Interval::OneHour, start, later);
+
+        // assert
+        assert_eq!(cache.dir.join("yahoo_AAPL_1h_0_3600.tickercache"), actual);
+        assert_ne!(actual, actual_later);
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_source_tells_data_dirs_apart() {
+        // assign
+        let first = make_test_dir("cache_source_first");
+        let second = make_test_dir("cache_source_second");
+
+        // act
+        let yahoo = cache_source(ProviderKind::Yahoo, None);
+        let first_source = cache_source(ProviderKind::File, Some(&first));
+        let second_source = cache_source(ProviderKind::File, Some(&second));
+
+        // assert
+        assert_eq!("yahoo", yahoo);
+        assert!(first_source.starts_with("file-"));
+        assert_ne!(first_source, second_source);
+        assert_eq!(first_source, cache_source(ProviderKind::File, Some(&first)));
+        fs::remove_dir_all(&first).unwrap();
+        fs::remove_dir_all(&second).unwrap();
+    }
+
+    #[test]
+    fn caching_provider_serves_repeats_from_cache() {
+        // assign
+        let cache = test_cache("caching_provider_repeats", u64::MAX);
+        let inner = Arc::new(FlakyProvider {
+            failures: 0,
+            status: "",
+            calls: AtomicU32::new(0),
+        });
+        let provider =
+            CachingProvider::new(inner.clone(), ProviderKind::Yahoo, None, cache.clone());
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+
+        // act
+        let first = block_on(provider.get_quote_history("AAPL", start, start, Interval::OneDay));
+        let second = block_on(provider.get_quote_history("AAPL", start, start, Interval::OneDay));
+
+        // assert
+        assert_eq!(1, inner.calls.load(Ordering::SeqCst));
+        assert_eq!(first.unwrap(), second.unwrap());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn caching_provider_does_not_cache_failures() {
+        // assign
+        let cache = test_cache("caching_provider_failures", u64::MAX);
+        let inner = Arc::new(FlakyProvider {
+            failures: 1,
+            status: "500",
+            calls: AtomicU32::new(0),
+        });
+        let provider =
+            CachingProvider::new(inner.clone(), ProviderKind::Yahoo, None, cache.clone());
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+
+        // act
+        let first = block_on(provider.get_quote_history("AAPL", start, start, Interval::OneDay));
+        let second = block_on(provider.get_quote_history("AAPL", start, start, Interval::OneDay));
+
+        // assert
+        assert!(first.is_err());
+        assert!(second.is_ok());
+        assert_eq!(2, inner.calls.load(Ordering::SeqCst));
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_get_expired_entry() {
+        // assign
+        let cache = test_cache("cache_expired", u64::MAX);
+        let entry = cache
+            .dir
+            .join("yahoo_AAPL_1d_1970-01-01_1970-01-01.tickercache");
+        cache
+            .put(&entry, &QuoteHistory::new(vec![fake_quote(0, 1.0, 2.0)]))
+            .unwrap();
+        age_entry(&entry, Duration::from_secs(2 * 86400));
+
+        // act
+        let stats = cache.stats();
+        let actual = cache.get(&entry);
+
+        // assert
+        assert_eq!(1, stats.expired);
+        assert_eq!(None, actual);
+        assert!(!entry.exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_put_removes_oldest_over_size_cap() {
+        // assign
+        let history = QuoteHistory::new(vec![fake_quote(0, 1.0, 2.0)]);
+        let size = serde_json::to_string(&history).unwrap().len() as u64;
+        let cache = test_cache("cache_size_cap", size * 2);
+        let old = cache
+            .dir
+            .join("yahoo_AAPL_1d_1970-01-01_1970-01-01.tickercache");
+        let newer = cache
+            .dir
+            .join("yahoo_MSFT_1d_1970-01-01_1970-01-01.tickercache");
+        let newest = cache
+            .dir
+            .join("yahoo_GOOG_1d_1970-01-01_1970-01-01.tickercache");
+        cache.put(&old, &history).unwrap();
+        age_entry(&old, Duration::from_secs(60));
+        cache.put(&newer, &history).unwrap();
+
+        // act
+        cache.put(&newest, &history).unwrap();
+
+        // assert
+        assert!(!old.exists());
+        assert!(newer.exists());
+        assert!(newest.exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_evict_entry_already_removed() {
+        // assign
+        let cache = test_cache("cache_evict", u64::MAX);
+        let entry = cache
+            .dir
+            .join("yahoo_AAPL_1d_1970-01-01_1970-01-01.tickercache");
+        cache
+            .put(&entry, &QuoteHistory::new(vec![fake_quote(0, 1.0, 2.0)]))
+            .unwrap();
+
+        // act
+        let first = evict(&entry);
+        let second = evict(&entry);
+
+        // assert
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert!(!entry.exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn cache_stats_and_clear() {
+        // assign
+        let cache = test_cache("cache_stats_clear", u64::MAX);
+        let history = QuoteHistory::new(vec![fake_quote(0, 1.0, 2.0)]);
+        let size = serde_json::to_string(&history).unwrap().len() as u64;
+        cache
+            .put(&cache.dir.join("yahoo_AAPL_1d_a_b.tickercache"), &history)
+            .unwrap();
+        cache
+            .put(&cache.dir.join("yahoo_MSFT_1d_a_b.tickercache"), &history)
+            .unwrap();
+        fs::write(cache.dir.join("notes.txt"), "not a response").unwrap();
+        fs::write(cache.dir.join("AAPL.json"), "{}").unwrap();
+
+        // act
+        let stats = cache.stats();
+        let removed = cache.clear();
+
+        // assert
+        assert_eq!(
+            CacheStats {
+                entries: 2,
+                bytes: size * 2,
+                expired: 0
+            },
+            stats
+        );
+        assert_eq!(2, removed);
+        assert_eq!(0, cache.stats().entries);
+        assert!(cache.dir.join("notes.txt").exists());
+        assert!(cache.dir.join("AAPL.json").exists());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "cache directory does not exist")]
+    fn validate_cache_dir_missing() {
+        // assign
+        let cache_dir = Some(PathBuf::from("no_such_cache_dir"));
+
+        // act
+        validate_cache_dir(&cache_dir);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn summarise_gains() {
+        // assign
+        let gains = vec![0.1, -0.2, 0.3, 0.0];
+
+        // act
+        let actual = summarise("AAPL", &gains, 252.0).unwrap();
+
+        // assert
+        assert_eq!("AAPL", actual.symbol);
+        assert_eq!(4, actual.count);
+        assert!((actual.mean - 0.05).abs() < 1e-12);
+        assert!((actual.median - 0.05).abs() < 1e-12);
+        let std_dev = (0.13_f64 / 3.0).sqrt();
+        assert!((actual.std_dev - std_dev).abs() < 1e-12);
+        assert!((actual.volatility - std_dev * 252.0_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(0.3, actual.best);
+        assert_eq!(-0.2, actual.worst);
+        assert_eq!(50.0, actual.up_percent);
+        assert!((actual.max_drawdown - 0.2).abs() < 1e-12);
+        assert!((actual.sharpe - 0.05 / std_dev * 252.0_f64.sqrt()).abs() < 1e-12);
+        assert!((actual.sortino - 0.05 / 0.1 * 252.0_f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn summarise_no_gains() {
+        // assign
+        let gains: Vec<f64> = Vec::new();
+
+        // act
+        let actual = summarise("AAPL", &gains, 252.0);
+
+        // assert
+        assert!(actual.is_none());
+    }
+
+    #[test]
+    fn summarise_one_gain_has_no_ratios() {
+        // assign
+        let gains = vec![0.1];
+
+        // act
+        let actual = summarise("AAPL", &gains, 252.0).unwrap();
+
+        // assert
+        assert_eq!(0.0, actual.std_dev);
+        assert_eq!(0.0, actual.sharpe);
+        assert_eq!(0.0, actual.sortino);
+        assert_eq!(0.0, actual.max_drawdown);
+    }
+
+    #[test]
+    fn max_drawdown_from_later_peak() {
+        // assign
+        let gains = vec![-0.1, 1.0, -0.5, 0.2];
+
+        // act
+        let actual = max_drawdown(&gains);
+
+        // assert
+        assert!((actual - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn process_symbols_writes_summary() {
+        // assign
+        let dir = make_test_dir("summary");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86_400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            summary: true,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(
+            vec!["MSFT", "AAPL", "FAIL"],
+            &dir,
+            Some(2),
+            settings,
+            provider,
+        );
+
+        // assert
+        let actual = fs::read_to_string(dir.join("summary.csv")).unwrap();
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!(
+            "symbol,count,mean,median,std_dev,volatility,best,worst,up_percent,max_drawdown,sharpe,sortino",
+            lines[0]
+        );
+        assert!(lines[1].starts_with("AAPL,2,0.25,0.25,"));
+        assert!(lines[2].starts_with("MSFT,2,"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_symbols_without_summary() {
+        // assign
+        let dir = make_test_dir("no_summary");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0)],
+        });
+
+        // act
+        process_symbols(
+            vec!["AAPL"],
+            &dir,
+            None,
+            test_settings(OutputMode::Gains),
+            provider,
+        );
+
+        // assert
+        assert!(!dir.join("summary.csv").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn gain_series(gains: &[(&str, f64)]) -> GainSeries {
+        gains
+            .iter()
+            .map(|(date, gain)| (String::from(*date), *gain))
+            .collect()
+    }
+
+    #[test]
+    fn covariance_of_pairs() {
+        // assign
+        let pairs = vec![(1.0, 2.0), (2.0, 4.0), (3.0, 6.0)];
+
+        // act
+        let actual = covariance(&pairs);
+
+        // assert
+        assert_eq!(Some(2.0), actual);
+    }
+
+    #[test]
+    fn pearson_moves_together_and_apart() {
+        // assign
+        let together = vec![(0.01, 0.02), (-0.02, -0.04), (0.03, 0.06)];
+        let apart = vec![(0.01, -0.01), (-0.02, 0.02), (0.03, -0.03)];
+
+        // act
+        let actual_together = pearson(&together).unwrap();
+        let actual_apart = pearson(&apart).unwrap();
+
+        // assert
+        assert!((actual_together - 1.0).abs() < 1e-12);
+        assert!((actual_apart + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pearson_flat_series_is_none() {
+        // assign
+        let pairs = vec![(0.01, 0.0), (0.02, 0.0), (0.03, 0.0)];
+
+        // act
+        let actual = pearson(&pairs);
+
+        // assert
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn spearman_uses_ranks() {
+        // assign
+        let pairs = vec![(1.0, 1.0), (2.0, 8.0), (3.0, 27.0), (4.0, 1000.0)];
+
+        // act
+        let actual = spearman(&pairs).unwrap();
+
+        // assert
+        assert!((actual - 1.0).abs() < 1e-12);
+        assert!(pearson(&pairs).unwrap() < 1.0);
+    }
+
+    #[test]
+    fn ranks_share_ties() {
+        // assign
+        let values = vec![0.3, 0.1, 0.3, 0.2];
+
+        // act
+        let actual = ranks(&values);
+
+        // assert
+        assert_eq!(vec![3.5, 1.0, 3.5, 2.0], actual);
+    }
+
+    #[test]
+    fn aligned_drops_missing_days() {
+        // assign
+        let a = gain_series(&[
+            ("2024-01-01", 0.1),
+            ("2024-01-02", 0.2),
+            ("2024-01-03", 0.3),
+        ]);
+        let b = gain_series(&[
+            ("2024-01-01", 1.0),
+            ("2024-01-03", 3.0),
+            ("2024-01-04", 4.0),
+        ]);
+
+        // act
+        let actual = aligned(&a, &b);
+
+        // assert
+        assert_eq!(vec![(0.1, 1.0), (0.3, 3.0)], actual);
+    }
+
+    #[test]
+    fn build_statistic_matrix_pairwise() {
+        // assign
+        let mut series = BTreeMap::new();
+        series.insert(
+            String::from("MSFT"),
+            gain_series(&[
+                ("2024-01-01", 0.1),
+                ("2024-01-02", 0.2),
+                ("2024-01-03", 0.4),
+            ]),
+        );
+        series.insert(
+            String::from("AAPL"),
+            gain_series(&[
+                ("2024-01-01", 0.2),
+                ("2024-01-02", 0.4),
+                ("2024-01-03", 0.8),
+            ]),
+        );
+        series.insert(String::from("NEW"), gain_series(&[("2024-01-03", 0.1)]));
+
+        // act
+        let actual = build_statistic_matrix(&series, Statistic::Pearson);
+
+        // assert
+        let empty = Cell::Text(String::new());
+        assert_eq!(4, actual.len());
+        assert_eq!(
+            vec![
+                Cell::Text(String::from("symbol")),
+                Cell::Text(String::from("AAPL")),
+                Cell::Text(String::from("MSFT")),
+                Cell::Text(String::from("NEW")),
+            ],
+            actual[0]
+        );
+        assert_eq!(Cell::Text(String::from("AAPL")), actual[1][0]);
+        match &actual[1][2] {
+            Cell::Number(value) => assert!((value - 1.0).abs() < 1e-12),
+            other => panic!("not a number: {other:?}"),
+        }
+        assert_eq!(empty, actual[1][3]);
+        assert_eq!(empty, actual[3][3]);
+    }
+
+    #[test]
+    fn process_symbols_writes_correlation_matrices() {
+        // assign
+        let dir = make_test_dir("correlate");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![fake_quote(0, 1.0, 2.0), fake_quote(86_400, 2.0, 1.0)],
+        });
+        let settings = RunSettings {
+            correlate: true,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["MSFT", "AAPL"], &dir, None, settings, provider);
+
+        // assert
+        let actual = fs::read_to_string(dir.join("pearson.csv")).unwrap();
+        assert_eq!("symbol,AAPL,MSFT\nAAPL,1.0,1.0\nMSFT,1.0,1.0\n", actual);
+        assert!(dir.join("spearman.csv").exists());
+        let actual = fs::read_to_string(dir.join("covariance.csv")).unwrap();
+        assert_eq!(
+            "symbol,AAPL,MSFT\nAAPL,1.125,1.125\nMSFT,1.125,1.125\n",
+            actual
+        );
+        assert!(dir.join("AAPL").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn closes(closes: &[f64]) -> Vec<Quote> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(day, close)| fake_quote(day as u64 * 86_400, *close, *close))
+            .collect()
+    }
+
+    #[test]
+    fn indicator_from_str() {
+        // assign
+        let texts = [
+            "sma:20",
+            "EMA:5",
+            "rsi",
+            "macd",
+            "macd:5:35:5",
+            "bb:20:2.5",
+            "atr:14",
+        ];
+
+        // act
+        let actual: Vec<Indicator> = texts.iter().map(|text| text.parse().unwrap()).collect();
+
+        // assert
+        assert_eq!(
+            vec![
+                Indicator::Sma(20),
+                Indicator::Ema(5),
+                Indicator::Rsi(14),
+                Indicator::Macd {
+                    fast: 12,
+                    slow: 26,
+                    signal: 9
+                },
+                Indicator::Macd {
+                    fast: 5,
+                    slow: 35,
+                    signal: 5
+                },
+                Indicator::Bollinger {
+                    period: 20,
+                    width: 2.5
+                },
+                Indicator::Atr(14),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn indicator_from_str_bad() {
+        // assign
+        let texts = [
+            "sma:0",
+            "sma:x",
+            "sma:5:5",
+            "macd:26:12",
+            "bb:20:0",
+            "vwap:5",
+        ];
+
+        // act
+        let actual: Vec<Result<Indicator, String>> =
+            texts.iter().map(|text| text.parse()).collect();
+
+        // assert
+        assert!(actual.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn indicator_columns_in_order() {
+        // assign
+        let indicators = vec![
+            Indicator::Rsi(14),
+            Indicator::Macd {
+                fast: 12,
+                slow: 26,
+                signal: 9,
+            },
+            Indicator::Bollinger {
+                period: 20,
+                width: 2.0,
+            },
+        ];
+
+        // act
+        let actual = indicator_columns(&indicators);
+
+        // assert
+        assert_eq!(
+            vec![
+                "rsi_14",
+                "macd_12_26_9",
+                "macd_signal_12_26_9",
+                "macd_hist_12_26_9",
+                "bb_middle_20",
+                "bb_upper_20",
+                "bb_lower_20",
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn sma_values() {
+        // assign
+        let quotes = closes(&[1.0, 2.0, 3.0, 4.0]);
+
+        // act
+        let actual = Indicator::Sma(2).values(&quotes);
+
+        // assert
+        assert_eq!(vec![vec![None, Some(1.5), Some(2.5), Some(3.5)]], actual);
+    }
+
+    #[test]
+    fn ema_values_start_from_sma() {
+        // assign
+        let quotes = closes(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        // act
+        let actual = Indicator::Ema(3).values(&quotes);
+
+        // assert
+        assert_eq!(
+            vec![vec![None, None, Some(2.0), Some(3.0), Some(4.0)]],
+            actual
+        );
+    }
+
+    #[test]
+    fn rsi_values() {
+        // assign
+        let quotes = closes(&[1.0, 2.0, 3.0, 2.0]);
+
+        // act
+        let actual = Indicator::Rsi(2).values(&quotes);
+
+        // assert
+        assert_eq!(vec![vec![None, None, Some(100.0), Some(50.0)]], actual);
+    }
+
+    #[test]
+    fn macd_values_after_warmup() {
+        // assign
+        let indicator = Indicator::Macd {
+            fast: 2,
+            slow: 3,
+            signal: 2,
+        };
+        let quotes = closes(&[1.0, 2.0, 4.0, 8.0, 16.0, 32.0]);
+
+        // act
+        let actual = indicator.values(&quotes);
+
+        // assert
+        assert_eq!(3, indicator.warmup());
+        assert_eq!(3, actual.len());
+        assert_eq!(None, actual[0][1]);
+        assert!(actual[0][2].is_some());
+        assert_eq!(None, actual[1][2]);
+        assert!(actual[1][3].is_some());
+        let histogram = actual[0][5].unwrap() - actual[1][5].unwrap();
+        assert!((actual[2][5].unwrap() - histogram).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bollinger_values() {
+        // assign
+        let indicator = Indicator::Bollinger {
+            period: 2,
+            width: 2.0,
+        };
+        let quotes = closes(&[1.0, 3.0]);
+
+        // act
+        let actual = indicator.values(&quotes);
+
+        // assert
+        assert_eq!(
+            vec![
+                vec![None, Some(2.0)],
+                vec![None, Some(4.0)],
+                vec![None, Some(0.0)]
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn atr_values() {
+        // assign
+        let bar = |high, low, close| Quote {
+            high,
+            low,
+            ..fake_quote(0, close, close)
+        };
+        let quotes = vec![
+            bar(11.0, 9.0, 10.0),
+            bar(12.0, 10.0, 11.0),
+            bar(15.0, 12.0, 14.0),
+            bar(14.0, 13.0, 13.0),
+        ];
+
+        // act
+        let actual = Indicator::Atr(2).values(&quotes);
+
+        // assert
+        assert_eq!(vec![vec![None, None, Some(3.0), Some(2.0)]], actual);
+    }
+
+    #[test]
+    fn warmup_start_daily() {
+        // assign
+        let start = OffsetDateTime::from_unix_timestamp(100 * 86_400).unwrap();
+
+        // act
+        let actual = warmup_start(start, Interval::OneDay, 20);
+        let none = warmup_start(start, Interval::OneDay, 0);
+
+        // assert
+        assert_eq!(64 * 86_400, actual.unix_timestamp());
+        assert_eq!(start, none);
+    }
+
+    #[test]
+    fn process_symbols_with_indicators() {
+        // assign
+        let dir = make_test_dir("indicators");
+        let provider = Arc::new(FakeProvider {
+            quotes: closes(&[1.0, 2.0, 3.0, 4.0, 5.0]),
+        });
+        let start = Date::from_calendar_date(1970, Month::January, 3).unwrap();
+        let end = Date::from_calendar_date(1970, Month::January, 10).unwrap();
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(start),
+                Some(end),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            indicators: vec![Indicator::Sma(3)],
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        let actual = fs::read_to_string(dir.join("AAPL")).unwrap();
+        assert_eq!(
+            "date,gain,sma_3\n1970-01-03,0.0,2.0\n1970-01-04,0.0,3.0\n1970-01-05,0.0,4.0\n",
+            actual
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_document_indicators_null_during_warmup() {
+        // assign
+        let settings = RunSettings {
+            indicators: vec![Indicator::Sma(3)],
+            ..test_settings(OutputMode::Gains)
+        };
+        let rows = [OutputRow {
+            date: String::from("1970-01-01"),
+            quote: fake_quote(0, 1.0, 1.0),
+            gain: 0.0,
+            rolling: Vec::new(),
+            indicators: vec![None],
+        }];
+
+        // act
+        let actual = json_document("AAPL", &rows, &settings);
+
+        // assert
+        assert_eq!(serde_json::Value::Null, actual["rows"][0]["sma_3"]);
+        assert_eq!("1970-01-01", actual["rows"][0]["date"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "indicators need the csv, json or jsonl format")]
+    fn validate_indicators_run_wide_format() {
+        // assign
+        let indicators = vec![Indicator::Sma(20)];
+
+        // act
+        validate_indicators(&indicators, OutputFormat::Parquet);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn rolling_columns_per_window() {
+        // assign
+        let windows = vec![5, 20];
+
+        // act
+        let actual = rolling_columns(&windows);
+
+        // assert
+        assert_eq!(
+            vec![
+                "gain_mean_5",
+                "gain_std_5",
+                "gain_z_5",
+                "gain_min_5",
+                "gain_max_5",
+                "gain_mean_20",
+                "gain_std_20",
+                "gain_z_20",
+                "gain_min_20",
+                "gain_max_20",
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn rolling_values_over_window() {
+        // assign
+        let gains = vec![1.0, 3.0, 2.0];
+
+        // act
+        let actual = rolling_values(&[2], &gains);
+
+        // assert
+        assert_eq!(vec![None; 5], actual[0]);
+        let root_half = 0.5_f64.sqrt();
+        assert_eq!(Some(2.0), actual[1][0]);
+        assert!((actual[1][1].unwrap() - 2.0_f64.sqrt()).abs() < 1e-12);
+        assert!((actual[1][2].unwrap() - root_half).abs() < 1e-12);
+        assert_eq!((Some(1.0), Some(3.0)), (actual[1][3], actual[1][4]));
+        assert_eq!(Some(2.5), actual[2][0]);
+        assert!((actual[2][2].unwrap() + root_half).abs() < 1e-12);
+        assert_eq!((Some(2.0), Some(3.0)), (actual[2][3], actual[2][4]));
+    }
+
+    #[test]
+    fn rolling_values_flat_gains_have_no_z_score() {
+        // assign
+        let gains = vec![0.5, 0.5, 0.5];
+
+        // act
+        let actual = rolling_values(&[3], &gains);
+
+        // assert
+        assert_eq!(
+            vec![Some(0.5), Some(0.0), None, Some(0.5), Some(0.5)],
+            actual[2]
+        );
+    }
+
+    #[test]
+    fn process_symbols_with_rolling_and_indicators() {
+        // assign
+        let dir = make_test_dir("rolling");
+        let provider = Arc::new(FakeProvider {
+            quotes: vec![
+                fake_quote(0, 1.0, 2.0),
+                fake_quote(86_400, 1.0, 1.0),
+                fake_quote(2 * 86_400, 1.0, 3.0),
+                fake_quote(3 * 86_400, 1.0, 1.0),
+            ],
+        });
+        let start = Date::from_calendar_date(1970, Month::January, 3).unwrap();
+        let end = Date::from_calendar_date(1970, Month::January, 10).unwrap();
+        let settings = RunSettings {
+            range: DateRange::resolve(
+                Some(start),
+                Some(end),
+                None,
+                None,
+                OffsetDateTime::now_utc(),
+            ),
+            rolling: vec![2],
+            indicators: vec![Indicator::Sma(2)],
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["AAPL"], &dir, None, settings, provider);
+
+        // assert
+        let actual = fs::read_to_string(dir.join("AAPL")).unwrap();
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(
+            "date,gain,gain_mean_2,gain_std_2,gain_z_2,gain_min_2,gain_max_2,sma_2",
+            lines[0]
+        );
+        assert!(lines[1].starts_with("1970-01-03,2.0,1.0,"));
+        assert!(lines[1].ends_with(",0.0,2.0,2.0"));
+        assert_eq!(3, lines.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "rolling windows must be at least 2 long")]
+    fn validate_rolling_window_too_short() {
+        // assign
+        let windows = vec![5, 1];
+
+        // act
+        validate_rolling(&windows, OutputFormat::Csv);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "rolling windows need the csv, json or jsonl format")]
+    fn validate_rolling_run_wide_format() {
+        // assign
+        let windows = vec![5];
+
+        // act
+        validate_rolling(&windows, OutputFormat::Sqlite);
+
+        // assert
+        assert!(false);
+    }
+
+    fn metrics(values: &[(&str, Option<f64>)]) -> Vec<(String, Option<f64>)> {
+        values
+            .iter()
+            .map(|(name, value)| (String::from(*name), *value))
+            .collect()
+    }
+
+    #[test]
+    fn expression_from_str() {
+        // assign
+        let text = "volatility > 0.03 and last_gain < -0.05";
+
+        // act
+        let actual: Expression = text.parse().unwrap();
+
+        // assert
+        assert_eq!(
+            Expression::And(
+                Box::new(Expression::Compare(
+                    Operand::Metric(String::from("volatility")),
+                    Comparison::Greater,
+                    Operand::Number(0.03)
+                )),
+                Box::new(Expression::Compare(
+                    Operand::Metric(String::from("last_gain")),
+                    Comparison::Less,
+                    Operand::Number(-0.05)
+                ))
+            ),
+            actual
+        );
+    }
+
+    #[test]
+    fn expression_and_binds_tighter_than_or() {
+        // assign
+        let expression: Expression = "a > 1 or b > 1 and c > 1".parse().unwrap();
+        let grouped: Expression = "(a > 1 or b > 1) and c > 1".parse().unwrap();
+        let values = metrics(&[("a", Some(2.0)), ("b", Some(0.0)), ("c", Some(0.0))]);
+
+        // act
+        let actual = expression.matches(&values);
+        let actual_grouped = grouped.matches(&values);
+
+        // assert
+        assert!(actual);
+        assert!(!actual_grouped);
+    }
+
+    #[test]
+    fn expression_matches_comparisons() {
+        // assign
+        let values = metrics(&[("mean", Some(0.5)), ("rsi_14", Some(30.0))]);
+        let texts = [
+            ("mean >= 0.5", true),
+            ("mean <= 0.4", false),
+            ("mean == 0.5", true),
+            ("mean != 0.5", false),
+            ("not rsi_14 > 40", true),
+            ("rsi_14 < mean", false),
+            ("MEAN > 0 AND rsi_14 < 35", true),
+        ];
+
+        // act
+        let actual: Vec<bool> = texts
+            .iter()
+            .map(|(text, _)| text.parse::<Expression>().unwrap().matches(&values))
+            .collect();
+
+        // assert
+        let expected: Vec<bool> = texts.iter().map(|(_, expected)| *expected).collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn expression_missing_value_never_holds() {
+        // assign
+        let values = metrics(&[("rsi_14", None)]);
+        let expression: Expression = "rsi_14 < 30".parse().unwrap();
+
+        // act
+        let actual = expression.matches(&values);
+
+        // assert
+        assert!(!actual);
+    }
+
+    #[test]
+    fn expression_from_str_bad() {
+        // assign
+        let texts = [
+            "mean >",
+            "mean = 1",
+            "(mean > 1",
+            "mean > 1 extra",
+            "mean > 1 and",
+            "mean 1",
+            "-mean > 1",
+            "mean > $1",
+        ];
+
+        // act
+        let actual: Vec<Result<Expression, String>> =
+            texts.iter().map(|text| text.parse()).collect();
+
+        // assert
+        assert!(actual.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn metric_names_include_rolling_and_indicators() {
+        // assign
+        let settings = RunSettings {
+            rolling: vec![5],
+            indicators: vec![Indicator::Rsi(14)],
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        let actual = metric_names(&settings);
+
+        // assert
+        assert_eq!(Some(&String::from("count")), actual.first());
+        assert!(actual.contains(&String::from("last_gain")));
+        assert!(actual.contains(&String::from("gain_z_5")));
+        assert_eq!(Some(&String::from("rsi_14")), actual.last());
+    }
+
+    #[test]
+    fn symbol_metrics_from_rows() {
+        // assign
+        let settings = test_settings(OutputMode::Gains);
+        let rows = [
+            OutputRow {
+                date: String::from("1970-01-01"),
+                quote: fake_quote(0, 1.0, 2.0),
+                gain: 1.0,
+                rolling: Vec::new(),
+                indicators: Vec::new(),
+            },
+            OutputRow {
+                date: String::from("1970-01-02"),
+                quote: fake_quote(86_400, 2.0, 1.0),
+                gain: -0.5,
+                rolling: Vec::new(),
+                indicators: Vec::new(),
+            },
+        ];
+
+        // act
+        let actual = symbol_metrics("AAPL", &rows, &settings).unwrap();
+        let none = symbol_metrics("AAPL", &[], &settings);
+
+        // assert
+        let value = |name: &str| actual.iter().find(|(metric, _)| metric == name).unwrap().1;
+        assert_eq!(Some(2.0), value("count"));
+        assert_eq!(Some(0.25), value("mean"));
+        assert_eq!(Some(-0.5), value("last_gain"));
+        assert_eq!(Some(1.0), value("last_close"));
+        assert!(none.is_none());
+    }
+
+    /// provider that hands back rising quotes for UP and falling quotes for every other symbol
+    struct TrendProvider;
+
+    #[async_trait]
+    impl QuoteProvider for TrendProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            _start: OffsetDateTime,
+            _end: OffsetDateTime,
+            _interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let close = if symbol == "UP" { 2.0 } else { 0.5 };
+            Ok(QuoteHistory::new(vec![
+                fake_quote(0, 1.0, close),
+                fake_quote(86_400, 1.0, close),
+            ]))
+        }
+    }
+
+    #[test]
+    fn process_symbols_screen_writes_passing_symbols() {
+        // assign
+        let dir = make_test_dir("screen");
+        let settings = RunSettings {
+            screen: Some("mean > 0 and up_percent == 100".parse().unwrap()),
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(
+            vec!["DOWN", "UP"],
+            &dir,
+            None,
+            settings,
+            Arc::new(TrendProvider),
+        );
+
+        // assert
+        let actual = fs::read_to_string(dir.join("screen.csv")).unwrap();
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("symbol,count,mean,"));
+        assert!(lines[0].ends_with(",last_gain,last_close"));
+        assert!(lines[1].starts_with("UP,2.0,1.0,"));
+        assert!(!dir.join("UP").exists());
+        assert!(!dir.join("DOWN").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown metric in the filter: rsi_14")]
+    fn validate_screen_unknown_metric() {
+        // assign
+        let settings = RunSettings {
+            screen: Some("rsi_14 < 30".parse().unwrap()),
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        validate_screen(&settings);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "incremental cannot be used with ticker screen")]
+    fn validate_screen_incremental() {
+        // assign
+        let settings = RunSettings {
+            screen: Some("mean > 0".parse().unwrap()),
+            incremental: true,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        validate_screen(&settings);
+
+        // assert
+        assert!(false);
+    }
+
+    #[test]
+    fn process_symbols_screen_leaves_run_wide_file() {
+        // assign
+        let dir = make_test_dir("screen_matrix");
+        fs::write(dir.join("matrix.csv"), "date,UP\n1970-01-01,1.0\n").unwrap();
+        let settings = RunSettings {
+            screen: Some("last_gain > 100".parse().unwrap()),
+            format: OutputFormat::Matrix,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        process_symbols(vec!["UP"], &dir, None, settings, Arc::new(TrendProvider));
+
+        // assert
+        let actual = fs::read_to_string(dir.join("matrix.csv")).unwrap();
+        assert_eq!("date,UP\n1970-01-01,1.0\n", actual);
+        assert!(dir.join("screen.csv").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "ticker screen only writes screen.csv, the format cannot be set")]
+    fn validate_screen_run_wide_format() {
+        // assign
+        let settings = RunSettings {
+            screen: Some("mean > 0".parse().unwrap()),
+            format: OutputFormat::Parquet,
+            ..test_settings(OutputMode::Gains)
+        };
+
+        // act
+        validate_screen(&settings);
+
+        // assert
+        assert!(false);
+    }
 }
@@ -0,0 +1,116 @@
+pub mod vcr {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+    use yahoo_finance_api::time::OffsetDateTime;
+
+    use crate::log;
+    use crate::quote_provider::quote_provider::{
+        Interval, ProviderError, QuoteHistory, QuoteProvider,
+    };
+
+    /// One recorded provider response, stored as SYMBOL.json in the fixture directory
+    #[derive(Serialize, Deserialize)]
+    pub struct Fixture {
+        pub symbol: String,
+        pub start: i64,
+        pub end: i64,
+        pub interval: Interval,
+        pub response: Result<QuoteHistory, ProviderError>,
+    }
+
+    /// the file a symbol's fixture is kept in
+    fn fixture_file(dir: &Path, symbol: &str) -> PathBuf {
+        dir.join(format!("{symbol}.json"))
+    }
+
+    /// Provider that passes requests through to another provider and saves every response as a fixture
+    pub struct RecordingProvider {
+        inner: Arc<dyn QuoteProvider>,
+        fixture_dir: PathBuf,
+    }
+
+    impl RecordingProvider {
+        pub fn new(inner: Arc<dyn QuoteProvider>, fixture_dir: PathBuf) -> Self {
+            RecordingProvider { inner, fixture_dir }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for RecordingProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let fixture = Fixture {
+                symbol: String::from(symbol),
+                start: start.unix_timestamp(),
+                end: end.unix_timestamp(),
+                interval,
+                response: self
+                    .inner
+                    .get_quote_history(symbol, start, end, interval)
+                    .await,
+            };
+            match serde_json::to_string_pretty(&fixture) {
+                Err(e) => log(symbol, e),
+                Ok(json) => {
+                    if let Err(e) = fs::write(fixture_file(&self.fixture_dir, symbol), json) {
+                        log(symbol, e);
+                    }
+                }
+            }
+            fixture.response
+        }
+    }
+
+    /// Provider that serves back the responses saved by a RecordingProvider, without going to the network
+    pub struct ReplayProvider {
+        fixture_dir: PathBuf,
+    }
+
+    impl ReplayProvider {
+        pub fn new(fixture_dir: PathBuf) -> Self {
+            ReplayProvider { fixture_dir }
+        }
+    }
+
+    #[async_trait]
+    impl QuoteProvider for ReplayProvider {
+        async fn get_quote_history(
+            &self,
+            symbol: &str,
+            start: OffsetDateTime,
+            end: OffsetDateTime,
+            interval: Interval,
+        ) -> Result<QuoteHistory, ProviderError> {
+            let file_name = fixture_file(&self.fixture_dir, symbol);
+            let contents = fs::read_to_string(&file_name).map_err(|_| {
+                ProviderError::NotFound(format!("no fixture at {}", file_name.display()))
+            })?;
+            let fixture: Fixture =
+                serde_json::from_str(&contents).map_err(|e| ProviderError::Data(e.to_string()))?;
+            if fixture.start != start.unix_timestamp()
+                || fixture.end != end.unix_timestamp()
+                || fixture.interval != interval
+            {
+                log(
+                    symbol,
+                    format!(
+                        "replaying fixture recorded for {} - {} at {}",
+                        fixture.start, fixture.end, fixture.interval
+                    ),
+                );
+            }
+            fixture.response
+        }
+    }
+}